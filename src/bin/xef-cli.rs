@@ -0,0 +1,889 @@
+use std::{
+    fs::{self, File},
+    io,
+    path::PathBuf,
+};
+
+use anyhow::{Context, Result};
+use chrono::{Days, Local, NaiveDate};
+use clap::{Args, Parser, Subcommand};
+use uuid::Uuid;
+use xef::{
+    amount_format_logic1,
+    database::{self, Backup},
+    journal,
+    model::{
+        error::InventoryError,
+        inventory::RemovalPolicy,
+        item::Item,
+        price::{PricePolicy, Purchase},
+        supplier::{Offer, Supplier},
+    },
+    report::CostSheet,
+    spreadsheet,
+    validation::{
+        amount_validation, density_validation, loss_validation,
+        measure_validation, name_validation, price_validation,
+        unit_mass_validation,
+    },
+    workspace::{Location, LEGACY_FILE},
+    AppState,
+};
+
+/// Edits an xef inventory from the shell. Items are named by id or,
+/// when it is unique, by name.
+#[derive(Parser)]
+struct Cli
+{
+    #[command(flatten)]
+    location: Location,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command
+{
+    /// Prints id, name, amount and rolled-up price of every item,
+    /// separated by tabs.
+    List,
+    /// Adds an item and prints its id.
+    Add
+    {
+        name: String,
+        /// Amount bought, e.g. "1 kg".
+        #[arg(long)]
+        amount: String,
+        #[arg(long)]
+        price: String,
+        #[command(flatten)]
+        details: Details,
+    },
+    /// Changes the given fields of an item.
+    Update
+    {
+        item: String,
+        #[arg(long)]
+        name: Option<String>,
+        /// Amount bought, e.g. "1 kg".
+        #[arg(long)]
+        amount: Option<String>,
+        /// Recorded as bought today.
+        #[arg(long)]
+        price: Option<String>,
+        /// How the price follows the purchases: latest, average or
+        /// average:N for the last N.
+        #[arg(long)]
+        price_policy: Option<PricePolicy>,
+        #[command(flatten)]
+        details: Details,
+    },
+    /// Records a purchase of an item, which then takes its price from
+    /// its purchases.
+    Buy
+    {
+        item: String,
+        /// Price paid for the whole quantity.
+        #[arg(long)]
+        price: String,
+        /// Amount bought; the item's own amount if omitted.
+        #[arg(long)]
+        quantity: Option<String>,
+        #[arg(long, default_value = "")]
+        supplier: String,
+        /// As YYYY-MM-DD; today if omitted.
+        #[arg(long)]
+        date: Option<NaiveDate>,
+    },
+    /// Adds, changes or lists the suppliers items are bought from.
+    Supplier
+    {
+        #[command(subcommand)]
+        command: SupplierCommand,
+    },
+    /// Sets what a supplier asks for a pack of an item, replacing its
+    /// previous offer.
+    Offer
+    {
+        item: String,
+        supplier: String,
+        /// Amount sold together, e.g. "5 kg".
+        #[arg(long, required_unless_present = "remove")]
+        pack: Option<String>,
+        /// Price of the whole pack.
+        #[arg(long, required_unless_present = "remove")]
+        price: Option<String>,
+        /// Days between ordering and delivery.
+        #[arg(long, default_value_t = 0)]
+        lead_time: u32,
+        /// Also buy the item from this supplier, recording the offer as
+        /// bought today.
+        #[arg(long)]
+        prefer: bool,
+        /// Drop the supplier's offer instead.
+        #[arg(long, conflicts_with_all = ["pack", "price", "prefer"])]
+        remove: bool,
+    },
+    /// Compares the offers for an item, cheapest per kg, l or unit
+    /// first: supplier, pack, price, unit price and lead time,
+    /// separated by tabs. The preferred one is marked with *.
+    Offers
+    {
+        item: String,
+    },
+    /// Buys an item from a supplier, recording its offer as bought
+    /// today; without a supplier, no supplier is preferred any more.
+    Prefer
+    {
+        item: String,
+        supplier: Option<String>,
+    },
+    /// Prints the purchases of an item, oldest first: date, supplier,
+    /// quantity and price, separated by tabs.
+    Prices
+    {
+        item: String,
+    },
+    /// Removes an item, refusing if a recipe uses it unless told what
+    /// to do with those portions.
+    Remove
+    {
+        item: String,
+        /// Delete the portions that use the item.
+        #[arg(long)]
+        cascade: bool,
+        /// Move the portions that use the item to another one.
+        #[arg(long, conflicts_with = "cascade")]
+        replace: Option<String>,
+    },
+    /// Sets how much of an ingredient a recipe uses, adding it to the
+    /// recipe if needed.
+    Portion
+    {
+        recipe: String,
+        ingredient: String,
+        /// e.g. "200 g" or "2 colher de sopa".
+        amount: String,
+    },
+    /// Prints the rolled-up amount and price of an item.
+    Cost
+    {
+        item: String,
+        /// Also print each raw ingredient, most expensive first.
+        #[arg(long)]
+        breakdown: bool,
+        /// With the prices paid up to this day, as YYYY-MM-DD.
+        #[arg(long, conflicts_with = "breakdown")]
+        date: Option<NaiveDate>,
+    },
+    /// Prints how the price of an item changed over a period: the day
+    /// of each change, the new price and how far it is from the first,
+    /// separated by tabs.
+    Trend
+    {
+        item: String,
+        /// First day, as YYYY-MM-DD; a year before the last if omitted.
+        #[arg(long)]
+        from: Option<NaiveDate>,
+        /// Last day, as YYYY-MM-DD; today if omitted.
+        #[arg(long)]
+        to: Option<NaiveDate>,
+    },
+    /// Writes the cost sheet of a recipe: its ingredients, their cost,
+    /// the total and the yield.
+    Report
+    {
+        item: String,
+        /// Standalone HTML instead of Markdown.
+        #[arg(long)]
+        html: bool,
+        /// Number of servings, to print the cost of each.
+        #[arg(long)]
+        servings: Option<u32>,
+        /// Where to write; standard output if omitted.
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Adds or updates items from a CSV price list with id, name,
+    /// amount and price columns, and optionally supplier and date; id
+    /// may be left empty.
+    Import
+    {
+        /// CSV file, or - for standard input.
+        file: PathBuf,
+    },
+    /// Writes every item as CSV, with its price per kg, l or unit.
+    Export
+    {
+        /// Where to write; standard output if omitted.
+        file: Option<PathBuf>,
+    },
+    /// Writes the saved changes as JSON lines, oldest first.
+    Journal
+    {
+        /// Only the changes to this item and its portions.
+        item: Option<String>,
+        /// Where to write; standard output if omitted.
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Lists the backups of the data file, newest first, numbered for
+    /// restore.
+    Backups,
+    /// Replaces the data file with one of its backups. The current
+    /// file is backed up first.
+    Restore
+    {
+        /// Number of the backup, as listed by backups.
+        backup: usize,
+    },
+}
+
+#[derive(Subcommand)]
+enum SupplierCommand
+{
+    /// Prints the id, name and contact of every supplier, separated by
+    /// tabs.
+    List,
+    /// Adds a supplier and prints its id.
+    Add
+    {
+        name: String,
+        #[arg(long, default_value = "")]
+        contact: String,
+    },
+    /// Changes the given fields of a supplier.
+    Update
+    {
+        supplier: String,
+        #[arg(long)]
+        name: Option<String>,
+        #[arg(long)]
+        contact: Option<String>,
+    },
+    /// Removes a supplier that no item has offers from.
+    Remove
+    {
+        supplier: String,
+    },
+}
+
+/// Optional fields of an item; an empty value clears them.
+#[derive(Args)]
+struct Details
+{
+    /// Density in g/ml.
+    #[arg(long)]
+    density: Option<String>,
+    /// Mass of one unit, e.g. "50 g".
+    #[arg(long)]
+    unit_mass: Option<String>,
+    /// Percentage lost to trimming.
+    #[arg(long)]
+    trim_loss: Option<String>,
+    /// Percentage lost while cooking.
+    #[arg(long)]
+    cooking_loss: Option<String>,
+}
+
+impl Command
+{
+    /// Whether the command only reads, so that nothing gets saved.
+    fn reads_only(&self) -> bool
+    {
+        matches!(
+            self,
+            Command::List
+                | Command::Cost { .. }
+                | Command::Trend { .. }
+                | Command::Report { .. }
+                | Command::Export { .. }
+                | Command::Journal { .. }
+                | Command::Offers { .. }
+                | Command::Prices { .. }
+                | Command::Backups
+                | Command::Supplier {
+                    command: SupplierCommand::List
+                }
+        )
+    }
+}
+
+impl Details
+{
+    fn apply(self, item: &mut Item) -> Result<()>
+    {
+        if let Some(text) = self.density {
+            item.density = density_validation(text)?;
+        }
+        if let Some(text) = self.unit_mass {
+            item.unit_mass = unit_mass_validation(text)?;
+        }
+        if let Some(text) = self.trim_loss {
+            item.trim_loss = loss_validation(text)?;
+        }
+        if let Some(text) = self.cooking_loss {
+            item.cooking_loss = loss_validation(text)?;
+        }
+        Ok(())
+    }
+}
+
+fn list(state: &AppState)
+{
+    let model = state.model.borrow();
+    for item in model.list_item() {
+        let price = model
+            .get_price(item.id)
+            .map_or_else(|e| e.to_string(), |p| format!("{:.2}", p));
+        println!(
+            "{}\t{}\t{}\t{}",
+            item.id,
+            item.name,
+            amount_format_logic1(item.amount),
+            price
+        );
+    }
+}
+
+fn cost(
+    state: &AppState,
+    key: &str,
+    breakdown: bool,
+    date: Option<NaiveDate>,
+) -> Result<()>
+{
+    let model = state.model.borrow();
+    let item = model.find_item(key)?;
+    let amount = model.get_amount(item.id)?;
+    let price = match date {
+        Some(date) => model.get_price_at(item.id, date)?,
+        None => model.get_price(item.id)?,
+    };
+    println!(
+        "{}\t{}\t{:.2}",
+        item.name,
+        amount_format_logic1(amount),
+        price
+    );
+    if breakdown {
+        for line in model.get_breakdown(item.id)?.leaves() {
+            println!(
+                "  {}\t{}\t{:.2}\t{:.1}%",
+                line.name,
+                amount_format_logic1(line.amount),
+                line.cost,
+                line.percent
+            );
+        }
+    }
+    Ok(())
+}
+
+fn trend(
+    state: &AppState,
+    key: &str,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+) -> Result<()>
+{
+    let to = to.unwrap_or_else(today);
+    let from = from.unwrap_or(to - Days::new(365));
+    let model = state.model.borrow();
+    let series =
+        model.get_price_series(model.find_item(key)?.id, from, to)?;
+    let Some(first) = series.first() else {
+        return Ok(());
+    };
+    for point in series.iter() {
+        // there is no drift from nothing
+        let drift = match point.price / first.price {
+            ratio if first.price > 0.0 && ratio.is_finite() => {
+                format!("{:+.1}%", (ratio - 1.0) * 100.0)
+            }
+            _ => "-".into(),
+        };
+        println!("{}\t{:.2}\t{}", point.date, point.price, drift);
+    }
+    Ok(())
+}
+
+fn add(
+    state: &mut AppState,
+    name: String,
+    amount: String,
+    price: String,
+    details: Details,
+) -> Result<()>
+{
+    let mut item = Item::builder()
+        .with_name(&name_validation(name)?)
+        .with_amount(amount_validation(amount)?)
+        .with_price(price_validation(price)?)
+        .build()?;
+    details.apply(&mut item)?;
+    if item.is_bought() {
+        item.add_purchase(Purchase::current(&item, today(), ""))?;
+    }
+    let id = item.id;
+    state.mutate(|model| model.add_item(item))?;
+    println!("{}", id);
+    Ok(())
+}
+
+fn update(
+    state: &mut AppState,
+    key: &str,
+    name: Option<String>,
+    amount: Option<String>,
+    price: Option<String>,
+    price_policy: Option<PricePolicy>,
+    details: Details,
+) -> Result<()>
+{
+    let mut item = state.model.borrow().find_item(key)?;
+    if let Some(text) = name {
+        item.name = name_validation(text)?;
+    }
+    if let Some(text) = amount {
+        item.amount = amount_validation(text)?;
+    }
+    if let Some(text) = price {
+        item.price = price_validation(text)?;
+    }
+    details.apply(&mut item)?;
+    let id = item.id;
+    state.mutate(|model| {
+        model.edit_item(id, item, today())?;
+        match price_policy {
+            Some(policy) => model.set_price_policy(id, policy),
+            None => Ok(()),
+        }
+    })?;
+    Ok(())
+}
+
+fn buy(
+    state: &mut AppState,
+    key: &str,
+    price: String,
+    quantity: Option<String>,
+    supplier: String,
+    date: Option<NaiveDate>,
+) -> Result<()>
+{
+    let item = state.model.borrow().find_item(key)?;
+    let quantity = match quantity {
+        Some(text) => amount_validation(text)?,
+        None => item.amount,
+    };
+    let purchase = Purchase {
+        date: date.unwrap_or_else(today),
+        supplier,
+        quantity,
+        price: price_validation(price)?,
+    };
+    state.mutate(|model| model.record_purchase(item.id, purchase))?;
+    Ok(())
+}
+
+fn supplier(state: &mut AppState, command: SupplierCommand) -> Result<()>
+{
+    match command {
+        SupplierCommand::List => {
+            for supplier in state.model.borrow().list_suppliers() {
+                println!(
+                    "{}\t{}\t{}",
+                    supplier.id, supplier.name, supplier.contact
+                );
+            }
+        }
+        SupplierCommand::Add { name, contact } => {
+            let mut supplier = Supplier::new(&name_validation(name)?);
+            supplier.contact = contact;
+            let id = supplier.id;
+            state.mutate(|model| model.add_supplier(supplier))?;
+            println!("{}", id);
+        }
+        SupplierCommand::Update {
+            supplier,
+            name,
+            contact,
+        } => {
+            let id = state.model.borrow().find_supplier(&supplier)?.id;
+            let name = name.map(name_validation).transpose()?;
+            state.mutate(|model| {
+                model.update_supplier(id, |supplier| {
+                    if let Some(name) = name {
+                        supplier.name = name;
+                    }
+                    if let Some(contact) = contact {
+                        supplier.contact = contact;
+                    }
+                })
+            })?;
+        }
+        SupplierCommand::Remove { supplier } => {
+            let id = state.model.borrow().find_supplier(&supplier)?.id;
+            state.mutate(|model| model.remove_supplier(&id))?;
+        }
+    }
+    Ok(())
+}
+
+/// The ids of the item `key` and of the supplier `supplier`.
+fn offer_ids(
+    state: &AppState,
+    key: &str,
+    supplier: &str,
+) -> Result<(Uuid, Uuid)>
+{
+    let model = state.model.borrow();
+    Ok((model.find_item(key)?.id, model.find_supplier(supplier)?.id))
+}
+
+fn offer(
+    state: &mut AppState,
+    key: &str,
+    supplier: &str,
+    pack: Option<String>,
+    price: Option<String>,
+    lead_time: u32,
+    prefer: bool,
+) -> Result<()>
+{
+    let (id, supplier_id) = offer_ids(state, key, supplier)?;
+    // clap requires both unless removing
+    let offer = Offer {
+        supplier_id,
+        pack: amount_validation(pack.unwrap_or_default())?,
+        price: price_validation(price.unwrap_or_default())?,
+        lead_time_days: lead_time,
+    };
+    state.mutate(|model| {
+        model.set_offer(id, offer, today())?;
+        if prefer {
+            model.set_preferred_supplier(
+                id,
+                Some(supplier_id),
+                today(),
+            )?;
+        }
+        Ok::<_, InventoryError>(())
+    })?;
+    Ok(())
+}
+
+fn remove_offer(
+    state: &mut AppState,
+    key: &str,
+    supplier: &str,
+) -> Result<()>
+{
+    let (id, supplier_id) = offer_ids(state, key, supplier)?;
+    state.mutate(|model| model.remove_offer(id, supplier_id))?;
+    Ok(())
+}
+
+fn offers(state: &AppState, key: &str) -> Result<()>
+{
+    let model = state.model.borrow();
+    for line in model.compare_offers(model.find_item(key)?.id)? {
+        let unit_price = match line.cost.display_unit_price() {
+            Some((price, unit)) => format!("{:.2}/{}", price, unit),
+            None => "-".into(),
+        };
+        println!(
+            "{}{}\t{}\t{:.2}\t{}\t{} d",
+            if line.preferred { "*" } else { "" },
+            line.supplier.name,
+            amount_format_logic1(line.offer.pack),
+            line.offer.price,
+            unit_price,
+            line.offer.lead_time_days
+        );
+    }
+    Ok(())
+}
+
+fn prefer(
+    state: &mut AppState,
+    key: &str,
+    supplier: Option<String>,
+) -> Result<()>
+{
+    let (id, supplier_id) = {
+        let model = state.model.borrow();
+        let supplier_id = match supplier {
+            Some(supplier) => Some(model.find_supplier(&supplier)?.id),
+            None => None,
+        };
+        (model.find_item(key)?.id, supplier_id)
+    };
+    state.mutate(|model| {
+        model.set_preferred_supplier(id, supplier_id, today())
+    })?;
+    Ok(())
+}
+
+fn prices(state: &AppState, key: &str) -> Result<()>
+{
+    let item = state.model.borrow().find_item(key)?;
+    for purchase in item.purchases.iter() {
+        println!(
+            "{}\t{}\t{}\t{:.2}",
+            purchase.date,
+            purchase.supplier,
+            amount_format_logic1(purchase.quantity),
+            purchase.price
+        );
+    }
+    Ok(())
+}
+
+fn remove(
+    state: &mut AppState,
+    key: &str,
+    cascade: bool,
+    replace: Option<String>,
+) -> Result<()>
+{
+    let (id, policy) = {
+        let model = state.model.borrow();
+        let policy = match replace {
+            Some(key) => RemovalPolicy::Replace(model.find_item(&key)?.id),
+            None if cascade => RemovalPolicy::Cascade,
+            None => RemovalPolicy::Refuse,
+        };
+        (model.find_item(key)?.id, policy)
+    };
+    state.mutate(|model| model.remove_item(&id, policy))?;
+    Ok(())
+}
+
+fn portion(
+    state: &mut AppState,
+    recipe: &str,
+    ingredient: &str,
+    amount: String,
+) -> Result<()>
+{
+    let (recipe, ingredient) = {
+        let model = state.model.borrow();
+        (model.find_item(recipe)?.id, model.find_item(ingredient)?.id)
+    };
+    let amount = measure_validation(amount)?;
+    state.mutate(|model| {
+        model.create_portion(ingredient, recipe, amount)
+    })?;
+    Ok(())
+}
+
+fn report(
+    state: &AppState,
+    key: &str,
+    html: bool,
+    servings: Option<u32>,
+    output: Option<PathBuf>,
+) -> Result<()>
+{
+    let model = state.model.borrow();
+    let sheet =
+        CostSheet::new(&model, model.find_item(key)?.id, servings)?;
+    let text = if html {
+        sheet.to_html()
+    } else {
+        sheet.to_markdown()
+    };
+    match output {
+        Some(path) => fs::write(path, text)?,
+        None => print!("{}", text),
+    }
+    Ok(())
+}
+
+fn import(state: &mut AppState, file: PathBuf) -> Result<()>
+{
+    let report = state.mutate(|model| {
+        if file.as_os_str() == "-" {
+            spreadsheet::import(model, io::stdin().lock())
+        } else {
+            spreadsheet::import(model, File::open(&file)?)
+        }
+    })?;
+    eprint!("{}", report);
+    Ok(())
+}
+
+fn export(state: &AppState, file: Option<PathBuf>) -> Result<()>
+{
+    let model = state.model.borrow();
+    match file {
+        Some(file) => spreadsheet::export(&model, File::create(file)?),
+        None => spreadsheet::export(&model, io::stdout().lock()),
+    }
+}
+
+fn journal(
+    state: &AppState,
+    key: Option<String>,
+    output: Option<PathBuf>,
+) -> Result<()>
+{
+    let Some(log) = &state.journal else {
+        return Ok(());
+    };
+    let entries = match key {
+        Some(key) => {
+            let id = state.model.borrow().find_item(&key)?.id;
+            log.for_item(id)?
+        }
+        None => log.read()?,
+    };
+    match output {
+        Some(path) => journal::export(&entries, File::create(path)?),
+        None => journal::export(&entries, io::stdout().lock()),
+    }
+}
+
+fn backups(state: &AppState) -> Result<Vec<Backup>>
+{
+    let Some(storage) = &state.storage else {
+        return Ok(vec![]);
+    };
+    Ok(database::list_backups(&storage.borrow().location())?)
+}
+
+fn list_backups(state: &AppState) -> Result<()>
+{
+    for (number, backup) in backups(state)?.iter().enumerate() {
+        println!(
+            "{}\t{}\t{}",
+            number + 1,
+            backup.taken_at.format("%Y-%m-%d %H:%M:%S"),
+            backup.path.display()
+        );
+    }
+    Ok(())
+}
+
+fn restore(state: &mut AppState, number: usize) -> Result<()>
+{
+    let backups = backups(state)?;
+    let backup = number
+        .checked_sub(1)
+        .and_then(|index| backups.get(index))
+        .with_context(|| format!("no backup {}", number))?;
+    state.restore(backup)?;
+    eprintln!("restored {}", backup.path.display());
+    Ok(())
+}
+
+fn today() -> NaiveDate { Local::now().date_naive() }
+
+fn main() -> Result<()>
+{
+    let cli = Cli::parse();
+    let mut state = AppState::default();
+    // repairs are only written when asked for
+    let writes = !cli.command.reads_only() || cli.location.repair;
+    if writes {
+        if let Some(path) = cli.location.import_legacy()? {
+            eprintln!("copied {} to {}", LEGACY_FILE, path.display());
+        }
+    }
+    if let Some(report) = cli.location.open(&mut state, writes)? {
+        eprint!("{}", report);
+        eprintln!("repaired the problems above");
+    }
+    match cli.command {
+        Command::List => list(&state),
+        Command::Add {
+            name,
+            amount,
+            price,
+            details,
+        } => add(&mut state, name, amount, price, details)?,
+        Command::Update {
+            item,
+            name,
+            amount,
+            price,
+            price_policy,
+            details,
+        } => update(
+            &mut state,
+            &item,
+            name,
+            amount,
+            price,
+            price_policy,
+            details,
+        )?,
+        Command::Buy {
+            item,
+            price,
+            quantity,
+            supplier,
+            date,
+        } => buy(&mut state, &item, price, quantity, supplier, date)?,
+        Command::Supplier { command } => supplier(&mut state, command)?,
+        Command::Offer {
+            item,
+            supplier,
+            remove: true,
+            ..
+        } => remove_offer(&mut state, &item, &supplier)?,
+        Command::Offer {
+            item,
+            supplier,
+            pack,
+            price,
+            lead_time,
+            prefer,
+            ..
+        } => offer(
+            &mut state, &item, &supplier, pack, price, lead_time, prefer,
+        )?,
+        Command::Offers { item } => offers(&state, &item)?,
+        Command::Prefer { item, supplier } => {
+            prefer(&mut state, &item, supplier)?
+        }
+        Command::Prices { item } => prices(&state, &item)?,
+        Command::Remove {
+            item,
+            cascade,
+            replace,
+        } => remove(&mut state, &item, cascade, replace)?,
+        Command::Portion {
+            recipe,
+            ingredient,
+            amount,
+        } => portion(&mut state, &recipe, &ingredient, amount)?,
+        Command::Cost {
+            item,
+            breakdown,
+            date,
+        } => cost(&state, &item, breakdown, date)?,
+        Command::Trend { item, from, to } => {
+            trend(&state, &item, from, to)?
+        }
+        Command::Report {
+            item,
+            html,
+            servings,
+            output,
+        } => report(&state, &item, html, servings, output)?,
+        Command::Import { file } => import(&mut state, file)?,
+        Command::Export { file } => export(&state, file)?,
+        Command::Journal { item, output } => {
+            journal(&state, item, output)?
+        }
+        Command::Backups => list_backups(&state)?,
+        Command::Restore { backup } => restore(&mut state, backup)?,
+    }
+    if state.dirty && writes {
+        state.save()?;
+    }
+    Ok(())
+}
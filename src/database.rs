@@ -1,46 +1,251 @@
 // #![allow(unused)]
-use std::fs::File;
+use std::{
+    cmp::Reverse,
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+};
 
 use anyhow::Result;
+use chrono::{Duration, Local, NaiveDateTime};
 use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
 
-use crate::model::{inventory::Inventory, item::Item, portion::Portion};
+use crate::model::{
+    error::InventoryError, inventory::Inventory, item::Item,
+    portion::Portion, supplier::Supplier,
+};
 
-fn save_to_file<T: Serialize>(filename: &str, data: &T) -> Result<()>
+/// How many backups are kept next to a data file.
+pub const MAX_BACKUPS: usize = 10;
+/// Saves closer than this to the newest backup don't take a new one.
+pub const BACKUP_INTERVAL: Duration = Duration::minutes(10);
+
+const BACKUP_STAMP: &str = "%Y%m%d-%H%M%S%.3f";
+
+fn write_file<T: Serialize>(path: &Path, data: &T) -> io::Result<()>
+{
+    let file = File::create(path)?;
+    let mut writer = BufWriter::new(file);
+    serde_json::to_writer(&mut writer, data)?;
+    writer.flush()?;
+    writer.get_ref().sync_all()
+}
+
+/// Writes `data` next to `filename` and renames it into place once it
+/// is on disk, so a crash leaves either the old or the new file. The
+/// old file is backed up first; `force` skips [`BACKUP_INTERVAL`].
+fn save_to_file<T: Serialize>(
+    filename: &str,
+    data: &T,
+    force: bool,
+) -> io::Result<()>
 {
-    let file = File::create(filename)?;
-    serde_json::to_writer(file, data)?;
+    let path = Path::new(filename);
+    let temp = path.with_file_name(format!("{}.tmp", file_name(path)));
+    let result = write_file(&temp, data).and_then(|()| {
+        if path.exists() {
+            take_backup(path, force)?;
+        }
+        fs::rename(&temp, path)
+    });
+    if let Err(e) = result {
+        let _ = fs::remove_file(&temp);
+        return Err(e);
+    }
+    #[cfg(unix)]
+    File::open(parent_dir(path))?.sync_all()?;
     Ok(())
 }
 
 fn load_from_file<T: for<'de> Deserialize<'de>>(
     filename: &str,
-) -> Result<T>
+) -> Result<T, InventoryError>
 {
     let file = File::open(filename)?;
     let data = serde_json::from_reader(file)?;
     Ok(data)
 }
 
-#[derive(Clone, Serialize, Deserialize)]
+/// Version of the data file written by this build.
+pub const VERSION: u64 = 1;
+
+/// Upgrades a document from the version at its index to the next one.
+const MIGRATIONS: [fn(&mut Value); VERSION as usize] = [migrate_v0];
+
+/// The `amount` of every entry in the `key` list of `document`.
+fn amounts<'a>(
+    document: &'a mut Value,
+    key: &str,
+) -> impl Iterator<Item = &'a mut Value>
+{
+    document[key]
+        .as_array_mut()
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.get_mut("amount"))
+}
+
+/// Version 0 files have no version field and may store item and
+/// portion amounts as bare kilogram numbers.
+fn migrate_v0(document: &mut Value)
+{
+    for amount in amounts(document, "items") {
+        if let Some(value) = amount.as_f64() {
+            *amount = json!({ "mass": value });
+        }
+    }
+    for amount in amounts(document, "portions") {
+        if let Some(value) = amount.as_f64() {
+            *amount = json!({ "value": value, "unit": "kilogram" });
+        }
+    }
+}
+
+/// Brings a document of any known version up to [`VERSION`].
+pub fn migrate(mut document: Value) -> Result<Value, InventoryError>
+{
+    if !document.is_object() {
+        // not a data file; leave it for serde to reject
+        return Ok(document);
+    }
+    let version = document["version"].as_u64().unwrap_or(0);
+    if version > VERSION {
+        return Err(InventoryError::UnsupportedVersion(version));
+    }
+    for migration in MIGRATIONS[version as usize..].iter() {
+        migration(&mut document);
+    }
+    document["version"] = VERSION.into();
+    Ok(document)
+}
+
+fn file_name(path: &Path) -> String
+{
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+fn parent_dir(path: &Path) -> &Path
+{
+    match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    }
+}
+
+/// A copy of a data file taken before it was overwritten.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Backup
+{
+    pub path: PathBuf,
+    pub taken_at: NaiveDateTime,
+}
+
+/// Backups of `filename`, newest first.
+pub fn list_backups(filename: &str) -> io::Result<Vec<Backup>>
+{
+    let path = Path::new(filename);
+    let prefix = format!("{}.", file_name(path));
+    let mut backups = vec![];
+    for entry in fs::read_dir(parent_dir(path))? {
+        let entry_path = entry?.path();
+        let name = file_name(&entry_path);
+        let stamp = name
+            .strip_prefix(&prefix)
+            .and_then(|rest| rest.strip_suffix(".bak"));
+        let taken_at = stamp.and_then(|stamp| {
+            NaiveDateTime::parse_from_str(stamp, BACKUP_STAMP).ok()
+        });
+        if let Some(taken_at) = taken_at {
+            backups.push(Backup {
+                path: entry_path,
+                taken_at,
+            });
+        }
+    }
+    backups.sort_by_key(|backup| Reverse(backup.taken_at));
+    Ok(backups)
+}
+
+/// Copies the current file at `path` aside, unless the newest backup
+/// is recent and `force` isn't set, and drops the oldest backups past
+/// [`MAX_BACKUPS`].
+fn take_backup(path: &Path, force: bool) -> io::Result<()>
+{
+    let filename = path.to_string_lossy();
+    let now = Local::now().naive_local();
+    let backups = list_backups(&filename)?;
+    let recent = !force
+        && backups
+            .first()
+            .is_some_and(|newest| now - newest.taken_at < BACKUP_INTERVAL);
+    if recent {
+        return Ok(());
+    }
+    let name =
+        format!("{}.{}.bak", file_name(path), now.format(BACKUP_STAMP));
+    fs::copy(path, path.with_file_name(name))?;
+    for old in backups.iter().skip(MAX_BACKUPS - 1) {
+        fs::remove_file(&old.path)?;
+    }
+    Ok(())
+}
+
+#[derive(Clone, Default, Serialize, Deserialize)]
 pub struct AppData
 {
     pub items: Vec<Item>,
     pub portions: Vec<Portion>,
+    #[serde(default)]
+    pub suppliers: Vec<Supplier>,
 }
 
 impl AppData
 {
-    pub fn save(&self, filename: &str) -> Result<()>
+    fn write(&self, filename: &str, force_backup: bool) -> io::Result<()>
+    {
+        let document = Document {
+            version: VERSION,
+            data: self,
+        };
+        save_to_file(filename, &document, force_backup)
+    }
+    pub fn save(&self, filename: &str) -> io::Result<()>
+    {
+        self.write(filename, false)
+    }
+    pub fn load(filename: &str) -> Result<Self, InventoryError>
+    {
+        let document: Value = load_from_file(filename)?;
+        Self::from_document(document)
+    }
+    /// Replaces `filename` with the contents of `backup`, returning
+    /// them. The replaced file is always backed up, however recent the
+    /// last backup is.
+    pub fn restore(filename: &str, backup: &Backup) -> Result<Self>
     {
-        save_to_file(filename, self)
+        let data = Self::load(&backup.path.to_string_lossy())?;
+        data.write(filename, true)?;
+        Ok(data)
     }
-    pub fn load(filename: &str) -> Result<Self>
+    /// Reads a parsed data file of any known version.
+    pub fn from_document(document: Value) -> Result<Self, InventoryError>
     {
-        load_from_file(filename)
+        let data = serde_json::from_value(migrate(document)?)?;
+        Ok(data)
     }
 }
 
+#[derive(Serialize)]
+struct Document<'a>
+{
+    version: u64,
+    #[serde(flatten)]
+    data: &'a AppData,
+}
+
 impl From<Inventory> for AppData
 {
     fn from(inventory: Inventory) -> Self
@@ -49,6 +254,108 @@ impl From<Inventory> for AppData
             inventory.list_item().into_iter().map(|c: Item| c).collect();
 
         let portions = inventory.get_all_portions();
-        AppData { items, portions }
+        let suppliers = inventory.list_suppliers();
+        AppData {
+            items,
+            portions,
+            suppliers,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use std::env;
+
+    use uuid::Uuid;
+
+    use super::*;
+
+    /// An empty directory and the data file to keep in it.
+    fn data_file() -> (PathBuf, String)
+    {
+        let dir = env::temp_dir().join(format!("xef-{}", Uuid::new_v4()));
+        fs::create_dir(&dir).unwrap();
+        let file = dir.join("xef.json").to_string_lossy().into_owned();
+        (dir, file)
+    }
+
+    fn data(names: &[&str]) -> AppData
+    {
+        AppData {
+            items: names.iter().map(|name| Item::new(name)).collect(),
+            ..AppData::default()
+        }
+    }
+
+    fn names(data: &AppData) -> Vec<String>
+    {
+        data.items.iter().map(|item| item.name.clone()).collect()
+    }
+
+    /// Leaves a backup of `filename` as if taken `age` ago.
+    fn old_backup(filename: &str, age: Duration)
+    {
+        let taken_at = Local::now().naive_local() - age;
+        let path =
+            format!("{}.{}.bak", filename, taken_at.format(BACKUP_STAMP));
+        fs::copy(filename, path).unwrap();
+    }
+
+    #[test]
+    fn recent_backups_are_not_repeated()
+    {
+        let (dir, file) = data_file();
+        data(&["a"]).save(&file).unwrap();
+        assert!(list_backups(&file).unwrap().is_empty());
+        data(&["b"]).save(&file).unwrap();
+        data(&["c"]).save(&file).unwrap();
+
+        let backups = list_backups(&file).unwrap();
+        assert_eq!(backups.len(), 1);
+        let backup = AppData::load(&backups[0].path.to_string_lossy());
+        assert_eq!(names(&backup.unwrap()), ["a"]);
+        assert_eq!(names(&AppData::load(&file).unwrap()), ["c"]);
+        assert!(!Path::new(&format!("{}.tmp", file)).exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn old_backups_are_rotated()
+    {
+        let (dir, file) = data_file();
+        data(&["a"]).save(&file).unwrap();
+        for days in 1..=MAX_BACKUPS as i64 + 2 {
+            old_backup(&file, Duration::days(days));
+        }
+        data(&["b"]).save(&file).unwrap();
+
+        let backups = list_backups(&file).unwrap();
+        assert_eq!(backups.len(), MAX_BACKUPS);
+        let oldest = backups.last().unwrap().taken_at;
+        let age = Local::now().naive_local() - oldest;
+        assert!(age < Duration::days(MAX_BACKUPS as i64));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn restoring_backs_up_the_current_file()
+    {
+        let (dir, file) = data_file();
+        data(&["a"]).save(&file).unwrap();
+        old_backup(&file, Duration::minutes(1));
+        data(&["b"]).save(&file).unwrap();
+        let backups = list_backups(&file).unwrap();
+        assert_eq!(backups.len(), 1);
+
+        let restored = AppData::restore(&file, &backups[0]).unwrap();
+        assert_eq!(names(&restored), ["a"]);
+        assert_eq!(names(&AppData::load(&file).unwrap()), ["a"]);
+        let backups = list_backups(&file).unwrap();
+        assert_eq!(backups.len(), 2);
+        let replaced = AppData::load(&backups[0].path.to_string_lossy());
+        assert_eq!(names(&replaced.unwrap()), ["b"]);
+        fs::remove_dir_all(dir).unwrap();
     }
 }
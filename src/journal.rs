@@ -0,0 +1,153 @@
+use std::{
+    env,
+    fs::{File, OpenOptions},
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::model::change::Change;
+
+/// A change as it was saved, with when and by whom.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Entry
+{
+    pub at: DateTime<Local>,
+    pub user: String,
+    #[serde(flatten)]
+    pub change: Change,
+}
+
+impl Entry
+{
+    /// Whether the change touched the item `id`, itself or as part of
+    /// a portion. Changes to suppliers touch no item.
+    pub fn concerns(&self, id: Uuid) -> bool
+    {
+        match &self.change {
+            Change::AddItem(item) | Change::RemoveItem(item) => {
+                item.id == id
+            }
+            Change::UpdateItem { after, .. } => after.id == id,
+            Change::AddPortion(portion)
+            | Change::RemovePortion(portion)
+            | Change::UpdatePortion { after: portion, .. } => {
+                portion.source_id == id || portion.component_id == id
+            }
+            Change::AddSupplier(_)
+            | Change::UpdateSupplier { .. }
+            | Change::RemoveSupplier(_) => false,
+        }
+    }
+}
+
+/// Who is making changes: `$XEF_USER`, or else the login name.
+pub fn current_user() -> String
+{
+    ["XEF_USER", "USER", "USERNAME"]
+        .iter()
+        .find_map(|key| env::var(key).ok().filter(|user| !user.is_empty()))
+        .unwrap_or_else(|| "unknown".into())
+}
+
+/// Append-only log of every change saved to a data file, one JSON
+/// object per line, kept next to it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Journal
+{
+    path: PathBuf,
+}
+
+impl Journal
+{
+    pub fn new(path: impl Into<PathBuf>) -> Self
+    {
+        Self { path: path.into() }
+    }
+
+    /// The journal of the data file at `path`.
+    pub fn beside(path: &Path) -> Self
+    {
+        let mut name = path.file_name().unwrap_or_default().to_owned();
+        name.push(".journal.jsonl");
+        Self::new(path.with_file_name(name))
+    }
+
+    pub fn path(&self) -> &Path { &self.path }
+
+    /// Records `changes` as made now by the current user.
+    pub fn append(&self, changes: &[Change]) -> Result<()>
+    {
+        if changes.is_empty() {
+            return Ok(());
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        let mut writer = BufWriter::new(file);
+        let at = Local::now();
+        let user = current_user();
+        for change in changes.iter() {
+            let entry = Entry {
+                at,
+                user: user.clone(),
+                change: change.clone(),
+            };
+            serde_json::to_writer(&mut writer, &entry)?;
+            writer.write_all(b"\n")?;
+        }
+        writer.flush()?;
+        writer.get_ref().sync_all()?;
+        Ok(())
+    }
+
+    /// Every entry, oldest first. A missing journal is empty.
+    pub fn read(&self) -> Result<Vec<Entry>>
+    {
+        let file = match File::open(&self.path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                return Ok(vec![])
+            }
+            Err(e) => return Err(e.into()),
+        };
+        let mut entries = vec![];
+        for (index, line) in BufReader::new(file).lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let entry =
+                serde_json::from_str(&line).with_context(|| {
+                    format!("{}:{}", self.path.display(), index + 1)
+                })?;
+            entries.push(entry);
+        }
+        Ok(entries)
+    }
+
+    /// The entries that touched the item `id`, oldest first.
+    pub fn for_item(&self, id: Uuid) -> Result<Vec<Entry>>
+    {
+        let mut entries = self.read()?;
+        entries.retain(|entry| entry.concerns(id));
+        Ok(entries)
+    }
+}
+
+/// Writes `entries` as JSON lines.
+pub fn export(entries: &[Entry], writer: impl Write) -> Result<()>
+{
+    let mut writer = BufWriter::new(writer);
+    for entry in entries.iter() {
+        serde_json::to_writer(&mut writer, entry)?;
+        writer.write_all(b"\n")?;
+    }
+    writer.flush()?;
+    Ok(())
+}
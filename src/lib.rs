@@ -1,16 +1,35 @@
-use std::{cell::RefCell, rc::Rc};
+use std::{cell::RefCell, fs, io, rc::Rc};
 
-use anyhow::Result;
-use database::AppData;
-use model::inventory::Inventory;
+use anyhow::{Context, Result};
+use database::{AppData, Backup};
+use journal::Journal;
+use model::{
+    change::Change,
+    error::InventoryError,
+    inventory::Inventory,
+    quantity::{Measure, Quantity, Unit},
+    validation::ValidationReport,
+};
+use storage::{Storage, StorageError};
 use uom::si::{
-    f64::Mass,
     mass::{gram, kilogram},
+    volume::{liter, milliliter},
 };
 use uuid::Uuid;
+use workspace::Workspaces;
 pub mod database;
+pub mod journal;
 pub mod model;
+pub mod report;
+pub mod spreadsheet;
+pub mod storage;
+pub mod validation;
+#[cfg(feature = "gui")]
 pub mod view;
+pub mod workspace;
+
+/// How many edits can be undone.
+const MAX_UNDO: usize = 100;
 
 #[derive(Clone, Default, Debug, PartialEq)]
 pub enum AppMode
@@ -27,35 +46,285 @@ pub struct AppState
 {
     pub model: Rc<RefCell<Inventory>>,
     pub mode: AppMode,
+    /// Whether the model changed since it was last saved.
+    pub dirty: bool,
+    /// Why the last save failed, until a save succeeds.
+    pub save_error: Option<String>,
+    /// Where the model is saved; nothing is written without one.
+    pub storage: Option<Rc<RefCell<Box<dyn Storage>>>>,
+    /// Log of the changes saved to the storage.
+    pub journal: Option<Journal>,
+    /// Counts the writes to the journal and the switches to another
+    /// one, so that views only read it again when it changed.
+    pub journal_revision: u64,
+    /// Counts the edits to the model and the switches to another one,
+    /// for views that are costly to work out again.
+    pub model_revision: u64,
+    /// Directory the workspace switcher lists and opens.
+    pub workspaces: Option<Workspaces>,
+    /// Name of the open workspace, if the model came from one.
+    pub workspace: Option<String>,
+    /// Whether the next save must rewrite everything, because the
+    /// model was replaced rather than changed.
+    rewrite: bool,
+    /// Changes made by each edit, oldest edit first.
+    undo: Vec<Vec<Change>>,
+    /// Edits undone since the last new one, last undone at the end.
+    redo: Vec<Vec<Change>>,
 }
 
 impl AppState
 {
-    pub fn set_data(&mut self, data: AppData) -> Result<()>
+    /// Saves the current model and switches to the one in `storage`,
+    /// which is created empty if it doesn't exist yet. Damaged data is
+    /// refused with `InventoryError::Invalid`, unless `repair` is set:
+    /// then what can't be loaded is dropped and the rest saved on the
+    /// next save. Returns what had to be fixed, if anything.
+    pub fn open(
+        &mut self,
+        mut storage: Box<dyn Storage>,
+        repair: bool,
+    ) -> Result<Option<ValidationReport>>
     {
-        let mut model = self.model.borrow_mut();
-        *model = data.try_into()?;
+        self.save()?;
+        let data = match storage.load() {
+            Ok(data) => data,
+            Err(StorageError::Data(InventoryError::Load(e)))
+                if e.kind() == io::ErrorKind::NotFound =>
+            {
+                let data = AppData::default();
+                storage.save(&data)?;
+                data
+            }
+            Err(e) => return Err(e.into()),
+        };
+        let (inventory, report) = Inventory::repair(data);
+        if report.is_fatal() && !repair {
+            return Err(InventoryError::Invalid(report).into());
+        }
+        self.journal = Some(Journal::beside(storage.path()));
+        self.journal_revision += 1;
+        self.storage = Some(Rc::new(RefCell::new(storage)));
+        self.workspace = None;
+        self.mode = AppMode::default();
+        self.save_error = None;
+        self.undo.clear();
+        self.redo.clear();
+        *self.model.borrow_mut() = inventory;
+        self.model_revision += 1;
+        // what is not fatal gets fixed the same way on every load, only
+        // repairs are written back
+        self.dirty = report.is_fatal();
+        self.rewrite = self.dirty;
+        Ok((!report.is_empty()).then_some(report))
+    }
+
+    /// Opens the workspace `name`, creating it if needed. See `open`
+    /// for `repair`.
+    pub fn open_workspace(
+        &mut self,
+        name: &str,
+        repair: bool,
+    ) -> Result<Option<ValidationReport>>
+    {
+        let workspaces =
+            self.workspaces.clone().context("no workspace directory")?;
+        let path = workspaces.path(name)?;
+        fs::create_dir_all(workspaces.dir())?;
+        let storage = storage::open(&path)?;
+        let report = self.open(storage, repair)?;
+        self.workspace = Some(name.to_string());
+        Ok(report)
+    }
+
+    /// Replaces the open data file with `backup` and reloads it,
+    /// dropping what wasn't saved.
+    pub fn restore(&mut self, backup: &Backup) -> Result<()>
+    {
+        let storage = self.storage.clone().context("no data file open")?;
+        let filename = storage.borrow().location();
+        let data = AppData::restore(&filename, backup)?;
+        let (inventory, _) = Inventory::repair(data);
+        *self.model.borrow_mut() = inventory;
+        self.model_revision += 1;
+        self.undo.clear();
+        self.redo.clear();
+        self.dirty = false;
+        self.rewrite = false;
         Ok(())
     }
+
+    /// Runs `change` on the model as one edit that can be undone,
+    /// marking it as not saved.
+    pub fn mutate<R>(
+        &mut self,
+        change: impl FnOnce(&mut Inventory) -> R,
+    ) -> R
+    {
+        self.dirty = true;
+        let mut model = self.model.borrow_mut();
+        let start = model.changes().len();
+        let result = change(&mut model);
+        let changes = model.changes()[start..].to_vec();
+        drop(model);
+        if !changes.is_empty() {
+            self.model_revision += 1;
+            if self.undo.len() == MAX_UNDO {
+                self.undo.remove(0);
+            }
+            self.undo.push(changes);
+            self.redo.clear();
+        }
+        result
+    }
+
+    pub fn can_undo(&self) -> bool { !self.undo.is_empty() }
+
+    pub fn can_redo(&self) -> bool { !self.redo.is_empty() }
+
+    /// Applies `changes` in order as a single edit, returning the
+    /// changes it made. When one fails, those before it are reverted.
+    fn replay(
+        &mut self,
+        changes: impl Iterator<Item = Change>,
+    ) -> Result<Vec<Change>, InventoryError>
+    {
+        let mut model = self.model.borrow_mut();
+        let start = model.changes().len();
+        for change in changes {
+            if let Err(e) = model.apply(&change) {
+                model.revert_changes(start)?;
+                return Err(e);
+            }
+        }
+        let changes = model.changes()[start..].to_vec();
+        drop(model);
+        self.dirty = true;
+        self.model_revision += 1;
+        // the mode may point at an item the edit took away
+        let model = self.model.borrow();
+        let exists = |id: &Uuid| model.get_node(id).is_ok();
+        let valid = match &self.mode {
+            AppMode::InsertMode => true,
+            AppMode::EditMode(id) => exists(id),
+            AppMode::PortionMode(recipe, ingredient)
+            | AppMode::EditPortionMode(recipe, ingredient) => {
+                exists(recipe) && exists(ingredient)
+            }
+        };
+        drop(model);
+        if !valid {
+            self.mode = AppMode::default();
+        }
+        Ok(changes)
+    }
+
+    /// Reverts the last edit, returning whether there was one.
+    pub fn undo(&mut self) -> Result<bool, InventoryError>
+    {
+        let Some(changes) = self.undo.pop() else {
+            return Ok(false);
+        };
+        let inverse = changes.iter().rev().map(Change::inverse);
+        match self.replay(inverse) {
+            Ok(done) => self.redo.push(done),
+            Err(e) => {
+                self.undo.push(changes);
+                return Err(e);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Makes the last undone edit again, returning whether there was
+    /// one.
+    pub fn redo(&mut self) -> Result<bool, InventoryError>
+    {
+        let Some(changes) = self.redo.pop() else {
+            return Ok(false);
+        };
+        let inverse = changes.iter().rev().map(Change::inverse);
+        match self.replay(inverse) {
+            Ok(done) => self.undo.push(done),
+            Err(e) => {
+                self.redo.push(changes);
+                return Err(e);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Writes the pending changes to the storage and the journal,
+    /// keeping the error around for the UI when it fails.
+    pub fn save(&mut self) -> Result<()>
+    {
+        let Some(storage) = self.storage.clone() else {
+            return Ok(());
+        };
+        let result: Result<()> = {
+            let model = self.model.borrow();
+            let mut storage = storage.borrow_mut();
+            let result = if self.rewrite {
+                storage.save(&model.clone().into())
+            } else {
+                storage.commit(&model, model.changes())
+            };
+            result.map_err(Into::into)
+        };
+        // the data is saved even if the journal can't be written, so
+        // the changes are only reported, not kept for the next save
+        let result = result.and_then(|()| {
+            self.rewrite = false;
+            self.dirty = false;
+            let changes = self.model.borrow().changes().to_vec();
+            self.model.borrow_mut().clear_changes();
+            match &self.journal {
+                Some(journal) if !changes.is_empty() => {
+                    self.journal_revision += 1;
+                    journal.append(&changes)
+                }
+                _ => Ok(()),
+            }
+        });
+        self.save_error =
+            result.as_ref().err().map(|e| format!("{:#}", e));
+        result
+    }
 }
 
 //out
-pub fn mass_format_logic1(value: f64) -> String
+pub fn amount_format_logic1(amount: Quantity) -> String
 {
-    let mass = Mass::new::<kilogram>(value);
     let parse = |v| {
         format!("{:.2}", v)
             .trim_end_matches('0')
             .trim_end_matches('.')
             .to_string()
     };
-    if value > 0.999 {
-        let value: f64 = parse(mass.value).parse().unwrap_or(-1.1);
-        format!("{} kg", value)
-    } else {
-        let value: f64 = parse(mass.get::<gram>()).parse().unwrap_or(-1.1);
-        format!("{} g", value)
-    }
+    let (value, unit) = match amount {
+        Quantity::Mass(mass) if mass.value > 0.999 => {
+            (mass.get::<kilogram>(), "kg")
+        }
+        Quantity::Mass(mass) => (mass.get::<gram>(), "g"),
+        Quantity::Volume(volume) if volume.get::<liter>() > 0.999 => {
+            (volume.get::<liter>(), "l")
+        }
+        Quantity::Volume(volume) => (volume.get::<milliliter>(), "ml"),
+        Quantity::Count(count) => (count, "un"),
+    };
+    let value: f64 = parse(value).parse().unwrap_or(-1.1);
+    format!("{} {}", value, unit)
+}
+/// `amount` in kg, l or un with all of its digits, for files that are
+/// read back.
+pub fn amount_format_exact(amount: Quantity) -> String
+{
+    let (value, unit) = match amount {
+        Quantity::Mass(mass) => (mass.get::<kilogram>(), "kg"),
+        Quantity::Volume(volume) => (volume.get::<liter>(), "l"),
+        Quantity::Count(count) => (count, "un"),
+    };
+    format!("{} {}", value, unit)
 }
 pub fn clip_uuid(id: Uuid, index: usize) -> String
 {
@@ -65,14 +334,119 @@ pub fn clip_uuid(id: Uuid, index: usize) -> String
 }
 
 //in
-pub fn parse_mass_amount(text: String) -> Option<Mass>
+pub fn parse_measure(text: String) -> Option<Measure>
 {
     let index = text.find(' ')?;
     let (value, unit) = text.split_at(index);
     let amount: f64 = value.parse().ok()?;
-    match unit.trim_start() {
-        "g" | "grama" | "gramas" => Some(Mass::new::<gram>(amount)),
-        "kg" | "kilo" | "kilos" => Some(Mass::new::<kilogram>(amount)),
-        _ => None,
+    let unit = Unit::parse(unit.trim_start())?;
+    Some(Measure::new(amount, unit))
+}
+pub fn parse_amount(text: String) -> Option<Quantity>
+{
+    parse_measure(text).map(|measure| measure.quantity())
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::model::{
+        inventory::RemovalPolicy, item::Item, quantity::QuantityKind,
+    };
+
+    /// A kilogram of `name` for `price`.
+    fn ingredient(name: &str, price: f64) -> Item
+    {
+        Item::builder()
+            .with_name(name)
+            .with_amount(Quantity::of(QuantityKind::Mass, 1.0))
+            .with_price(price)
+            .build()
+            .unwrap()
+    }
+
+    fn price(state: &AppState, id: Uuid) -> f64
+    {
+        state.model.borrow().get_price(id).unwrap()
+    }
+
+    #[test]
+    fn undoing_a_cascade_removal_restores_the_price()
+    {
+        let mut state = AppState::default();
+        let items = [
+            ingredient("flour", 5.0),
+            ingredient("sugar", 10.0),
+            Item::new("dough"),
+            Item::new("cake"),
+        ];
+        let [flour, sugar, dough, cake] = items.each_ref().map(|i| i.id);
+        let grams = |value| Measure::new(value, Unit::Gram);
+        state
+            .mutate(|model| {
+                for item in items {
+                    model.add_item(item)?;
+                }
+                model.create_portion(flour, dough, grams(500.0))?;
+                model.create_portion(sugar, dough, grams(500.0))?;
+                model.create_portion(dough, cake, grams(500.0))?;
+                model.create_portion(sugar, cake, grams(200.0))?;
+                Ok::<_, InventoryError>(())
+            })
+            .unwrap();
+        assert!((price(&state, cake) - 5.75).abs() < 1e-9);
+
+        state
+            .mutate(|model| {
+                model.remove_item(&dough, RemovalPolicy::Cascade)
+            })
+            .unwrap();
+        assert!((price(&state, cake) - 2.0).abs() < 1e-9);
+
+        assert!(state.undo().unwrap());
+        assert!((price(&state, dough) - 7.5).abs() < 1e-9);
+        assert!((price(&state, cake) - 5.75).abs() < 1e-9);
+
+        assert!(state.redo().unwrap());
+        assert!(state.model.borrow().get_item(&dough).is_err());
+        assert!((price(&state, cake) - 2.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn a_failed_undo_changes_nothing()
+    {
+        let mut state = AppState::default();
+        let salt = ingredient("salt", 2.0);
+        let (soup, stew) = (Item::new("soup"), Item::new("stew"));
+        let [salt_id, soup_id, stew_id] = [salt.id, soup.id, stew.id];
+        let grams = |value| Measure::new(value, Unit::Gram);
+        state
+            .mutate(|model| {
+                model.add_item(soup)?;
+                model.add_item(stew)
+            })
+            .unwrap();
+        state
+            .mutate(|model| {
+                model.add_item(salt)?;
+                model.create_portion(salt_id, soup_id, grams(10.0))
+            })
+            .unwrap();
+        // outside the undo history, so that the salt can't be removed
+        state
+            .model
+            .borrow_mut()
+            .create_portion(salt_id, stew_id, grams(5.0))
+            .unwrap();
+        let changes = state.model.borrow().changes().len();
+
+        // the portion in the soup goes first, then the salt fails
+        let result = state.undo();
+        assert!(matches!(result, Err(InventoryError::InUse { .. })));
+        assert_eq!(state.model.borrow().changes().len(), changes);
+        assert!((price(&state, soup_id) - 0.02).abs() < 1e-9);
+        assert!(state.can_undo());
+        assert!(!state.can_redo());
     }
 }
@@ -1,43 +1,95 @@
-use std::ops::Deref;
+use std::time::Duration;
 
 use anyhow::Result;
+use clap::Parser;
 use floem::{
+    action::exec_after,
     event::{Event, EventListener},
-    reactive::{create_rw_signal, provide_context, use_context, RwSignal},
+    reactive::{
+        create_effect, create_rw_signal, provide_context, use_context,
+        RwSignal,
+    },
     views::Decorators,
 };
-use xef::{database::AppData, view::app_view, AppState};
+use xef::{
+    view::app_view,
+    workspace::{Location, LEGACY_FILE},
+    AppState,
+};
+
+/// Recipe costing for small kitchens.
+#[derive(Parser)]
+struct Args
+{
+    #[command(flatten)]
+    location: Location,
+}
 
-static DBFN: &str = "xef.json";
+/// Quiet time after the last change before it is saved.
+const AUTOSAVE_DELAY: Duration = Duration::from_secs(2);
 
-fn try_persist() -> Result<()>
+fn try_persist(state: RwSignal<AppState>) -> Result<()>
 {
-    let state: RwSignal<AppState> = use_context().unwrap();
-    let model = state.get().model.borrow().deref().clone();
-    let data: AppData = model.into();
-    data.save(DBFN)
+    let mut result = Ok(());
+    state.update(|state| result = state.save());
+    result
 }
 
 fn shutdown(_event: &Event) -> ()
 {
-    try_persist().expect("failed to persist");
+    let state: RwSignal<AppState> = use_context().unwrap();
+    if state.get_untracked().dirty {
+        if let Err(e) = try_persist(state) {
+            eprintln!("failed to persist: {:#}", e);
+        }
+    }
+}
+
+/// Saves the model once it has gone [`AUTOSAVE_DELAY`] without
+/// changes. Failed saves stay dirty and are retried the same way.
+fn autosave(state: RwSignal<AppState>)
+{
+    let pending = create_rw_signal(0_u64);
+    create_effect(move |_| {
+        if !state.with(|state| state.dirty) {
+            return;
+        }
+        let change = pending.get_untracked() + 1;
+        pending.set(change);
+        exec_after(AUTOSAVE_DELAY, move |_| {
+            if pending.get_untracked() == change {
+                if let Err(e) = try_persist(state) {
+                    eprintln!("failed to persist: {:#}", e);
+                }
+            }
+        });
+    });
 }
 
-fn try_init() -> Result<()>
+fn try_init(args: Args) -> Result<()>
 {
     let mut state = AppState::default();
-    if let Ok(data) = AppData::load(DBFN) {
-        state.set_data(data)?;
+    if let Some(path) = args.location.import_legacy()? {
+        eprintln!("copied {} to {}", LEGACY_FILE, path.display());
+    }
+    let report = args.location.open(&mut state, true)?;
+    if let (Some(report), Some(storage)) = (report, &state.storage) {
+        eprint!("{}", report);
+        eprintln!(
+            "opened {} after repairing the problems above",
+            storage.borrow().location()
+        );
     }
 
     let state_handle = create_rw_signal(state);
     provide_context(state_handle);
+    autosave(state_handle);
     Ok(())
 }
 
 fn main() -> Result<()>
 {
-    try_init()?;
+    try_init(Args::parse())?;
 
     floem::launch(|| {
         app_view().on_event_stop(EventListener::WindowClosed, shutdown)
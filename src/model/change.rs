@@ -0,0 +1,68 @@
+use serde::{Deserialize, Serialize};
+
+use super::{item::Item, portion::Portion, supplier::Supplier};
+
+/// A single mutation of an `Inventory`, carrying the values on both
+/// sides so it can be written to storage or reverted.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "change", rename_all = "snake_case")]
+pub enum Change
+{
+    AddItem(Item),
+    UpdateItem
+    {
+        before: Item,
+        after: Item,
+    },
+    RemoveItem(Item),
+    AddPortion(Portion),
+    UpdatePortion
+    {
+        before: Portion,
+        after: Portion,
+    },
+    RemovePortion(Portion),
+    AddSupplier(Supplier),
+    UpdateSupplier
+    {
+        before: Supplier,
+        after: Supplier,
+    },
+    RemoveSupplier(Supplier),
+}
+
+impl Change
+{
+    /// The change that undoes this one.
+    pub fn inverse(&self) -> Change
+    {
+        match self.clone() {
+            Change::AddItem(item) => Change::RemoveItem(item),
+            Change::UpdateItem { before, after } => Change::UpdateItem {
+                before: after,
+                after: before,
+            },
+            Change::RemoveItem(item) => Change::AddItem(item),
+            Change::AddPortion(portion) => Change::RemovePortion(portion),
+            Change::UpdatePortion { before, after } => {
+                Change::UpdatePortion {
+                    before: after,
+                    after: before,
+                }
+            }
+            Change::RemovePortion(portion) => Change::AddPortion(portion),
+            Change::AddSupplier(supplier) => {
+                Change::RemoveSupplier(supplier)
+            }
+            Change::UpdateSupplier { before, after } => {
+                Change::UpdateSupplier {
+                    before: after,
+                    after: before,
+                }
+            }
+            Change::RemoveSupplier(supplier) => {
+                Change::AddSupplier(supplier)
+            }
+        }
+    }
+}
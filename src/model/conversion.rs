@@ -0,0 +1,164 @@
+use thiserror::Error;
+use uom::si::f64::{Mass, Volume};
+use uuid::Uuid;
+
+use super::{
+    item::Item,
+    quantity::{Quantity, QuantityKind},
+};
+
+//--- kitchen measures, in milliliters
+pub const CUP: f64 = 240.0;
+pub const TABLESPOON: f64 = 15.0;
+pub const TEASPOON: f64 = 5.0;
+
+#[derive(Clone, Debug, PartialEq, Error)]
+#[error(
+    "cannot convert {from:?} of {name} ({item}) to {to:?} without its {}",
+    self.missing()
+)]
+pub struct ConversionError
+{
+    pub item: Uuid,
+    pub name: String,
+    pub from: QuantityKind,
+    pub to: QuantityKind,
+}
+
+impl ConversionError
+{
+    fn missing(&self) -> &'static str
+    {
+        use QuantityKind::*;
+        match (self.from, self.to) {
+            (Count, Volume) | (Volume, Count) => "density and unit weight",
+            (Count, _) | (_, Count) => "unit weight",
+            _ => "density",
+        }
+    }
+}
+
+fn to_mass(item: &Item, quantity: Quantity) -> Option<Mass>
+{
+    match quantity {
+        Quantity::Mass(mass) => Some(mass),
+        Quantity::Volume(volume) => Some(volume * item.density?),
+        Quantity::Count(count) => Some(item.unit_mass? * count),
+    }
+}
+
+fn from_mass(
+    item: &Item,
+    mass: Mass,
+    kind: QuantityKind,
+) -> Option<Quantity>
+{
+    match kind {
+        QuantityKind::Mass => Some(Quantity::Mass(mass)),
+        QuantityKind::Volume => {
+            let volume: Volume = mass / item.density?;
+            Some(Quantity::Volume(volume))
+        }
+        QuantityKind::Count => {
+            let count = (mass / item.unit_mass?).value;
+            Some(Quantity::Count(count))
+        }
+    }
+}
+
+/// Expresses `quantity` of `item` in `kind`, going through mass with
+/// the item's density or unit weight when the kinds differ.
+pub fn convert(
+    item: &Item,
+    quantity: Quantity,
+    kind: QuantityKind,
+) -> Result<Quantity, ConversionError>
+{
+    if quantity.kind() == kind {
+        return Ok(quantity);
+    }
+    to_mass(item, quantity)
+        .and_then(|mass| from_mass(item, mass, kind))
+        .ok_or(ConversionError {
+            item: item.id,
+            name: item.name.to_string(),
+            from: quantity.kind(),
+            to: kind,
+        })
+}
+
+#[cfg(test)]
+mod tests
+{
+    use uom::si::{
+        f64::MassDensity, mass::gram,
+        mass_density::gram_per_cubic_centimeter, volume::milliliter,
+    };
+
+    use super::*;
+
+    fn milk() -> Item
+    {
+        let mut item = Item::new("milk");
+        item.density =
+            Some(MassDensity::new::<gram_per_cubic_centimeter>(1.03));
+        item
+    }
+
+    fn egg() -> Item
+    {
+        let mut item = Item::new("egg");
+        item.unit_mass = Some(Mass::new::<gram>(50.0));
+        item
+    }
+
+    fn close(a: f64, b: f64) -> bool { (a - b).abs() < 1e-9 }
+
+    #[test]
+    fn same_kind_is_unchanged()
+    {
+        let quantity = Quantity::Count(3.0);
+        assert_eq!(
+            convert(&Item::new("x"), quantity, QuantityKind::Count),
+            Ok(quantity)
+        );
+    }
+
+    #[test]
+    fn volume_and_mass_go_through_density()
+    {
+        let cup = Quantity::Volume(Volume::new::<milliliter>(CUP));
+        let mass = convert(&milk(), cup, QuantityKind::Mass).unwrap();
+        assert!(close(mass.value(), 0.2472));
+        let back = convert(&milk(), mass, QuantityKind::Volume).unwrap();
+        assert!(close(back.value(), cup.value()));
+    }
+
+    #[test]
+    fn count_goes_through_unit_mass()
+    {
+        let dozen = Quantity::Count(12.0);
+        let mass = convert(&egg(), dozen, QuantityKind::Mass).unwrap();
+        assert!(close(mass.value(), 0.6));
+        let back = convert(&egg(), mass, QuantityKind::Count).unwrap();
+        assert!(close(back.value(), 12.0));
+    }
+
+    #[test]
+    fn missing_properties_are_named()
+    {
+        let cup = Quantity::Volume(Volume::new::<milliliter>(CUP));
+        let error = convert(&egg(), cup, QuantityKind::Mass).unwrap_err();
+        assert_eq!(error.missing(), "density");
+        assert_eq!(error.from, QuantityKind::Volume);
+
+        let dozen = Quantity::Count(12.0);
+        let error =
+            convert(&milk(), dozen, QuantityKind::Mass).unwrap_err();
+        assert_eq!(error.missing(), "unit weight");
+
+        let error = convert(&egg(), cup, QuantityKind::Count).unwrap_err();
+        assert_eq!(error.missing(), "density and unit weight");
+        assert!(error.to_string().ends_with("density and unit weight"));
+    }
+}
@@ -0,0 +1,178 @@
+use chrono::NaiveDate;
+use thiserror::Error;
+use uuid::Uuid;
+
+use super::{
+    conversion::ConversionError,
+    quantity::{Quantity, Unit},
+};
+
+/// Why the cost of an item could not be worked out.
+#[derive(Clone, Debug, PartialEq, Error)]
+pub enum CostError
+{
+    /// The item yields nothing, so it has no price per unit.
+    #[error("{name} ({item}) has no yield")]
+    ZeroYield
+    {
+        item: Uuid,
+        name: String,
+    },
+    /// The item's price is not a finite number.
+    #[error("{name} ({item}) has an invalid price")]
+    InvalidPrice
+    {
+        item: Uuid,
+        name: String,
+    },
+    /// The item is, through its portions, an ingredient of itself.
+    #[error("{name} ({item}) is part of a cycle")]
+    Cycle
+    {
+        item: Uuid,
+        name: String,
+    },
+    #[error(transparent)]
+    Conversion(#[from] ConversionError),
+}
+
+impl CostError
+{
+    /// The ingredient that broke the rollup.
+    pub fn item(&self) -> (Uuid, &str)
+    {
+        match self {
+            CostError::ZeroYield { item, name }
+            | CostError::InvalidPrice { item, name }
+            | CostError::Cycle { item, name } => (*item, name),
+            CostError::Conversion(e) => (e.item, &e.name),
+        }
+    }
+}
+
+/// Rolled-up price of an item on a day, a point of its cost over
+/// time.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CostPoint
+{
+    pub date: NaiveDate,
+    pub price: f64,
+}
+
+/// Rolled-up yield and price of an item, its recipe included.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct NodeCost
+{
+    pub amount: Quantity,
+    pub price: f64,
+}
+
+impl NodeCost
+{
+    /// Price per base unit (kg, m³ or unit) of the item, if it yields
+    /// anything.
+    pub fn unit_price(&self) -> Option<f64>
+    {
+        let amount = self.amount.value();
+        let unit_price = self.price / amount;
+        (amount > 0.0 && unit_price.is_finite()).then_some(unit_price)
+    }
+
+    /// Price per kg, l or unit, with that unit's symbol, as it is
+    /// shown to people.
+    pub fn display_unit_price(&self) -> Option<(f64, &'static str)>
+    {
+        let unit_price = self.unit_price()?;
+        Some(match self.amount {
+            Quantity::Mass(_) => (unit_price, "kg"),
+            Quantity::Volume(_) => {
+                (unit_price * Unit::Liter.quantity(1.0).value(), "l")
+            }
+            Quantity::Count(_) => (unit_price, "un"),
+        })
+    }
+}
+
+pub type CostResult = Result<NodeCost, CostError>;
+
+/// Where the cost of a recipe comes from: the part of each component
+/// used by its parent, expanded down to the raw ingredients.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BreakdownNode
+{
+    pub item_id: Uuid,
+    pub name: String,
+    /// Amount of the item used, in the kind it is measured in.
+    pub amount: Quantity,
+    /// Cost of that amount, components included.
+    pub cost: f64,
+    /// Part of `amount` and `cost` that comes from the item itself
+    /// rather than from its components.
+    pub own_amount: Quantity,
+    pub own_cost: f64,
+    /// Share of the root's total cost.
+    pub percent: f64,
+    pub children: Vec<BreakdownNode>,
+}
+
+/// Everything a recipe needs of one raw ingredient, summed over all
+/// the paths that reach it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BreakdownLine
+{
+    pub item_id: Uuid,
+    pub name: String,
+    pub amount: Quantity,
+    pub cost: f64,
+    pub percent: f64,
+}
+
+impl BreakdownNode
+{
+    fn collect(&self, total: f64, lines: &mut Vec<BreakdownLine>)
+    {
+        let is_leaf = self.children.is_empty();
+        if is_leaf || self.own_cost != 0.0 {
+            let (amount, cost) = if is_leaf {
+                (self.amount, self.cost)
+            } else {
+                (self.own_amount, self.own_cost)
+            };
+            let percent = if total > 0.0 {
+                100.0 * cost / total
+            } else {
+                0.0
+            };
+            match lines.iter_mut().find(|l| l.item_id == self.item_id) {
+                Some(line) => {
+                    line.amount = Quantity::of(
+                        amount.kind(),
+                        line.amount.value() + amount.value(),
+                    );
+                    line.cost += cost;
+                    line.percent += percent;
+                }
+                None => lines.push(BreakdownLine {
+                    item_id: self.item_id,
+                    name: self.name.to_string(),
+                    amount,
+                    cost,
+                    percent,
+                }),
+            }
+        }
+        for child in self.children.iter() {
+            child.collect(total, lines);
+        }
+    }
+
+    /// The raw ingredients of the recipe, most expensive first. A recipe
+    /// with a price of its own is listed alongside its components.
+    pub fn leaves(&self) -> Vec<BreakdownLine>
+    {
+        let mut lines = Vec::new();
+        self.collect(self.cost, &mut lines);
+        lines.sort_by(|a, b| b.cost.total_cmp(&a.cost));
+        lines
+    }
+}
@@ -0,0 +1,82 @@
+use thiserror::Error;
+use uuid::Uuid;
+
+use super::{
+    conversion::ConversionError, cost::CostError,
+    validation::ValidationReport,
+};
+
+pub type Result<T, E = InventoryError> = std::result::Result<T, E>;
+
+#[derive(Debug, Error)]
+pub enum InventoryError
+{
+    #[error("{0} not found")]
+    NotFound(Uuid),
+    #[error("{0} already exists")]
+    DuplicateItem(Uuid),
+    #[error("no item named {0}")]
+    NameNotFound(String),
+    #[error("more than one item is named {name}, use its id")]
+    AmbiguousName
+    {
+        name: String,
+        ids: Vec<Uuid>,
+    },
+    #[error("{ingredient} is not a portion of {recipe}")]
+    PortionNotFound
+    {
+        recipe: Uuid,
+        ingredient: Uuid,
+    },
+    #[error("adding {ingredient} to {recipe} would create a cycle")]
+    Cycle
+    {
+        recipe: Uuid,
+        ingredient: Uuid,
+    },
+    #[error("{item} is used by {}", .dependents.join(", "))]
+    InUse
+    {
+        item: Uuid,
+        dependents: Vec<String>,
+    },
+    #[error("no supplier named {0}")]
+    SupplierNotFound(String),
+    #[error("more than one supplier is named {name}, use its id")]
+    AmbiguousSupplierName
+    {
+        name: String,
+        ids: Vec<Uuid>,
+    },
+    #[error("{supplier} has offers for {}", .items.join(", "))]
+    SupplierInUse
+    {
+        supplier: Uuid,
+        items: Vec<String>,
+    },
+    #[error("{item} has no offer from {supplier}")]
+    OfferNotFound
+    {
+        item: Uuid,
+        supplier: Uuid,
+    },
+    #[error("{0} cannot replace itself")]
+    SelfReplacement(Uuid),
+    #[error("{0} is not a valid amount")]
+    InvalidAmount(String),
+    #[error("{0} is not a valid price")]
+    InvalidPrice(f64),
+    #[error(transparent)]
+    Conversion(#[from] ConversionError),
+    #[error(transparent)]
+    Cost(#[from] CostError),
+    #[error("failed to read data file")]
+    Load(#[from] std::io::Error),
+    #[error("failed to parse data file")]
+    Parse(#[from] serde_json::Error),
+    #[error("data file version {0} is newer than this program")]
+    UnsupportedVersion(u64),
+    #[error("invalid data file:\n{0}")]
+    Invalid(ValidationReport),
+}
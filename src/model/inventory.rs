@@ -1,25 +1,58 @@
 use std::{
     cell::{RefCell, RefMut},
+    collections::HashMap,
     rc::Rc,
 };
 
-use anyhow::{anyhow, Result};
+use chrono::NaiveDate;
 use indexmap::IndexMap;
 use petgraph::{
-    algo::is_cyclic_directed,
+    algo::{has_path_connecting, toposort},
     graph::{DiGraph, EdgeIndex, NodeIndex},
-    visit::EdgeRef,
+    visit::{Dfs, EdgeRef, Reversed},
+    Direction,
 };
 use uuid::Uuid;
 
-use super::{item::Item, portion::Portion};
+use super::{
+    change::Change,
+    conversion::convert,
+    cost::{BreakdownNode, CostError, CostPoint, CostResult, NodeCost},
+    error::{InventoryError, Result},
+    item::Item,
+    portion::Portion,
+    price::{PricePolicy, Purchase},
+    quantity::{Measure, Quantity},
+    supplier::{Offer, OfferComparison, Supplier},
+    validation::{
+        sanitize_item, sanitize_offers, ItemRef, Problem, ValidationReport,
+    },
+};
 use crate::database::AppData;
 
+/// What to do with the recipes that use an item being removed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RemovalPolicy
+{
+    /// Fail, listing the recipes that depend on the item.
+    Refuse,
+    /// Delete every portion that references the item.
+    Cascade,
+    /// Move every portion that references the item to another item.
+    Replace(Uuid),
+}
+
 #[derive(Clone, Default)]
 pub struct Inventory
 {
     graph: DiGraph<Rc<RefCell<Item>>, Portion>,
     nodes: IndexMap<Uuid, NodeIndex>,
+    /// Memoized rollup, filled by `evaluate` and cleared for the
+    /// ancestors of whatever changes.
+    costs: RefCell<HashMap<Uuid, CostResult>>,
+    /// Mutations not yet written to storage.
+    changes: Vec<Change>,
+    suppliers: IndexMap<Uuid, Supplier>,
 }
 
 impl Inventory
@@ -29,12 +62,70 @@ impl Inventory
         Inventory {
             graph: DiGraph::new(),
             nodes: IndexMap::new(),
+            costs: RefCell::new(HashMap::new()),
+            changes: Vec::new(),
+            suppliers: IndexMap::new(),
+        }
+    }
+
+    /// Mutations made since the last call to `clear_changes`, oldest
+    /// first.
+    pub fn changes(&self) -> &[Change] { &self.changes }
+
+    pub fn clear_changes(&mut self) { self.changes.clear() }
+
+    /// Reverts the changes made after the first `len`, newest first,
+    /// and forgets them.
+    pub fn revert_changes(&mut self, len: usize) -> Result<()>
+    {
+        let made = self.changes.split_off(len);
+        for change in made.iter().rev() {
+            self.apply(&change.inverse())?;
+        }
+        self.changes.truncate(len);
+        Ok(())
+    }
+
+    /// Makes `change` again, recording it like any other mutation.
+    /// Removing an item fails while recipes still use it.
+    pub fn apply(&mut self, change: &Change) -> Result<()>
+    {
+        match change.clone() {
+            Change::AddItem(item) => self.add_item(item),
+            Change::UpdateItem { after, .. } => {
+                self.update_item(after.id, |mut item| *item = after)
+            }
+            Change::RemoveItem(item) => self
+                .remove_item(&item.id, RemovalPolicy::Refuse)
+                .map(|_| ()),
+            Change::AddPortion(portion) => self
+                .create_portion(
+                    portion.component_id,
+                    portion.source_id,
+                    portion.amount,
+                )
+                .map(|_| ()),
+            Change::UpdatePortion { after, .. } => self
+                .update_portion(
+                    after.component_id,
+                    after.source_id,
+                    after.amount,
+                )
+                .map(|_| ()),
+            Change::RemovePortion(portion) => self
+                .remove_portion(portion.component_id, portion.source_id),
+            Change::AddSupplier(supplier) => self.add_supplier(supplier),
+            Change::UpdateSupplier { after, .. } => self
+                .update_supplier(after.id, |supplier| *supplier = after),
+            Change::RemoveSupplier(supplier) => {
+                self.remove_supplier(&supplier.id).map(|_| ())
+            }
         }
     }
 
     pub fn get_node(&self, id: &Uuid) -> Result<&NodeIndex>
     {
-        self.nodes.get(id).ok_or(anyhow!("{} not found", id))
+        self.nodes.get(id).ok_or(InventoryError::NotFound(*id))
     }
 
     pub fn get_nodes(&self) -> im::Vector<NodeIndex>
@@ -60,25 +151,149 @@ impl Inventory
             // Ok(item)
             Ok(self.graph[*node].borrow().clone())
         } else {
-            Err(anyhow!(format!("{} not found", id)))
+            Err(InventoryError::NotFound(*id))
         }
     }
 
-    pub fn remove_item(&mut self, id: &Uuid) -> Result<()>
+    /// The item with id `key`, or else the only one named `key`.
+    pub fn find_item(&self, key: &str) -> Result<Item>
     {
-        let _ = self.nodes.shift_remove(id).ok_or(anyhow!(format!(
-            "failed to remove node index for {:?}",
-            id
-        )))?;
-        Ok(())
+        if let Ok(id) = Uuid::parse_str(key) {
+            if self.nodes.contains_key(&id) {
+                return self.get_item(&id);
+            }
+        }
+        let matches: Vec<Item> = self
+            .list_item()
+            .into_iter()
+            .filter(|item| item.name == key)
+            .collect();
+        match matches.as_slice() {
+            [] => Err(InventoryError::NameNotFound(key.to_string())),
+            [item] => Ok(item.clone()),
+            _ => Err(InventoryError::AmbiguousName {
+                name: key.to_string(),
+                ids: matches.iter().map(|item| item.id).collect(),
+            }),
+        }
+    }
+
+    /// Items whose recipes use `id` as an ingredient.
+    pub fn get_dependents(&self, id: &Uuid) -> Result<im::Vector<Item>>
+    {
+        let node = self.get_node(id)?;
+        let dependents = self
+            .graph
+            .edges_directed(*node, Direction::Incoming)
+            .map(|edge| self.graph[edge.source()].borrow().clone())
+            .collect();
+        Ok(dependents)
+    }
+
+    /// Removes the item node together with its own portions, handling
+    /// the recipes that use it according to `policy`.
+    pub fn remove_item(
+        &mut self,
+        id: &Uuid,
+        policy: RemovalPolicy,
+    ) -> Result<Item>
+    {
+        let node = *self.get_node(id)?;
+        let incoming: Vec<EdgeIndex> = self
+            .graph
+            .edges_directed(node, Direction::Incoming)
+            .map(|edge| edge.id())
+            .collect();
+
+        match policy {
+            RemovalPolicy::Refuse if !incoming.is_empty() => {
+                let dependents: Vec<String> = self
+                    .get_dependents(id)?
+                    .into_iter()
+                    .map(|item| format!("{} ({})", item.name, item.id))
+                    .collect();
+                return Err(InventoryError::InUse {
+                    item: *id,
+                    dependents,
+                });
+            }
+            RemovalPolicy::Refuse | RemovalPolicy::Cascade => {}
+            RemovalPolicy::Replace(replacement_id) => {
+                if replacement_id == *id {
+                    return Err(InventoryError::SelfReplacement(
+                        replacement_id,
+                    ));
+                }
+                let replacement = *self.get_node(&replacement_id)?;
+                let item = self.graph[replacement].borrow().clone();
+                for edge in incoming.iter() {
+                    let (source, _) =
+                        self.graph.edge_endpoints(*edge).unwrap();
+                    if self.would_cycle(source, replacement) {
+                        return Err(InventoryError::Cycle {
+                            recipe: self.graph[source].borrow().id,
+                            ingredient: replacement_id,
+                        });
+                    }
+                    if let Some(existing) =
+                        self.find_portion(source, replacement)
+                    {
+                        let amount = self.graph[existing].amount;
+                        merge_amounts(
+                            &item,
+                            amount,
+                            self.graph[*edge].amount,
+                        )?;
+                    }
+                }
+                for edge in incoming.iter() {
+                    let (source, _) =
+                        self.graph.edge_endpoints(*edge).unwrap();
+                    let amount = self.graph[*edge].amount;
+                    self.merge_portion(source, replacement, amount)?;
+                }
+            }
+        }
+
+        let removed: Vec<Portion> = self
+            .graph
+            .edges_directed(node, Direction::Incoming)
+            .chain(self.graph.edges(node))
+            .map(|edge| edge.weight().clone())
+            .collect();
+        self.changes
+            .extend(removed.into_iter().map(Change::RemovePortion));
+
+        self.invalidate(node);
+        self.costs.borrow_mut().remove(id);
+
+        // `remove_node` moves the last node into the freed index
+        let last = NodeIndex::new(self.graph.node_count() - 1);
+        let item = self
+            .graph
+            .remove_node(node)
+            .ok_or(InventoryError::NotFound(*id))?;
+        self.nodes.shift_remove(id);
+        if last != node {
+            let moved_id = self.graph[node].borrow().id;
+            self.nodes.insert(moved_id, node);
+        }
+
+        let item = item.borrow().clone();
+        self.changes.push(Change::RemoveItem(item.clone()));
+        Ok(item)
     }
 
     pub fn add_item(&mut self, item: Item) -> Result<()>
     {
+        if self.nodes.contains_key(&item.id) {
+            return Err(InventoryError::DuplicateItem(item.id));
+        }
         let node = Rc::new(RefCell::new(item.clone()));
         let item_id = item.id;
         let node_index = self.graph.add_node(node.clone());
         self.nodes.insert(item_id, node_index);
+        self.changes.push(Change::AddItem(item));
         Ok(())
     }
 
@@ -88,12 +303,75 @@ impl Inventory
         update_fn: impl FnOnce(RefMut<Item>),
     ) -> Result<()>
     {
-        let index = self.get_node(&id)?;
-        let item_mref = self.graph[*index].borrow_mut();
-        update_fn(item_mref);
+        let index = *self.get_node(&id)?;
+        let before = self.graph[index].borrow().clone();
+        update_fn(self.graph[index].borrow_mut());
+        let after = self.graph[index].borrow().clone();
+        self.changes.push(Change::UpdateItem { before, after });
+        self.invalidate(index);
         Ok(())
     }
 
+    /// Adds `purchase` to the price history of `id`, which then takes
+    /// its price from it.
+    pub fn record_purchase(
+        &mut self,
+        id: Uuid,
+        purchase: Purchase,
+    ) -> Result<()>
+    {
+        let item = self.get_item(&id)?;
+        self.replace_item(id, item, Some(purchase))
+    }
+
+    /// Replaces the item `id` with `item`, keeping its price history
+    /// and offers.
+    /// A price or amount that changed is recorded as bought on `date`.
+    pub fn edit_item(
+        &mut self,
+        id: Uuid,
+        item: Item,
+        date: NaiveDate,
+    ) -> Result<()>
+    {
+        let old = self.get_item(&id)?;
+        let changed = item.price != old.price || item.amount != old.amount;
+        let purchase = (changed && item.is_bought()).then(|| Purchase {
+            date,
+            supplier: String::new(),
+            quantity: item.amount,
+            price: item.price,
+        });
+        self.replace_item(id, item, purchase)
+    }
+
+    /// Replaces the item `id` with `item`, keeping its price history
+    /// and offers, and adds `purchase` to them. Nothing changes if the
+    /// purchase can't be recorded.
+    pub fn replace_item(
+        &mut self,
+        id: Uuid,
+        item: Item,
+        purchase: Option<Purchase>,
+    ) -> Result<()>
+    {
+        let old = self.get_item(&id)?;
+        let mut item = Item {
+            purchases: old.purchases,
+            price_policy: old.price_policy,
+            offers: old.offers,
+            preferred_supplier: old.preferred_supplier,
+            ..item
+        };
+        match purchase {
+            Some(purchase) => buy(&mut item, purchase)?,
+            // the amount or density may change what the purchases add
+            // up to
+            None => item.reprice()?,
+        }
+        self.update_item(id, |mut current| *current = item)
+    }
+
     pub fn list_item(&self) -> im::Vector<Item>
     {
         self.nodes
@@ -104,24 +382,237 @@ impl Inventory
             .collect()
     }
 
-    pub fn get_portion(&self, from: Uuid, to: Uuid) -> Result<Portion>
+    pub fn list_suppliers(&self) -> Vec<Supplier>
     {
-        let source = self.get_node(&to)?;
-        let component = self.get_node(&from)?;
+        self.suppliers.values().cloned().collect()
+    }
 
-        let portion: Portion = self
-            .graph
-            .edges_connecting(*source, *component)
-            .map(|ei| ei.weight())
+    pub fn get_supplier(&self, id: &Uuid) -> Result<Supplier>
+    {
+        self.suppliers
+            .get(id)
             .cloned()
-            .next()
-            .ok_or(anyhow!(
-                "failed to get portion of {:?} in {:?}",
-                from,
-                to
-            ))?;
+            .ok_or(InventoryError::NotFound(*id))
+    }
 
-        Ok(portion)
+    /// The supplier with id `key`, or else the only one named `key`.
+    pub fn find_supplier(&self, key: &str) -> Result<Supplier>
+    {
+        if let Ok(id) = Uuid::parse_str(key) {
+            if let Some(supplier) = self.suppliers.get(&id) {
+                return Ok(supplier.clone());
+            }
+        }
+        let matches: Vec<&Supplier> = self
+            .suppliers
+            .values()
+            .filter(|supplier| supplier.name == key)
+            .collect();
+        match matches.as_slice() {
+            [] => Err(InventoryError::SupplierNotFound(key.to_string())),
+            [supplier] => Ok((*supplier).clone()),
+            _ => Err(InventoryError::AmbiguousSupplierName {
+                name: key.to_string(),
+                ids: matches.iter().map(|supplier| supplier.id).collect(),
+            }),
+        }
+    }
+
+    pub fn add_supplier(&mut self, supplier: Supplier) -> Result<()>
+    {
+        self.suppliers.insert(supplier.id, supplier.clone());
+        self.changes.push(Change::AddSupplier(supplier));
+        Ok(())
+    }
+
+    pub fn update_supplier(
+        &mut self,
+        id: Uuid,
+        update_fn: impl FnOnce(&mut Supplier),
+    ) -> Result<()>
+    {
+        let supplier = self
+            .suppliers
+            .get_mut(&id)
+            .ok_or(InventoryError::NotFound(id))?;
+        let before = supplier.clone();
+        update_fn(supplier);
+        let after = supplier.clone();
+        self.changes.push(Change::UpdateSupplier { before, after });
+        Ok(())
+    }
+
+    /// Removes a supplier, failing while any item has an offer from
+    /// it.
+    pub fn remove_supplier(&mut self, id: &Uuid) -> Result<Supplier>
+    {
+        let supplier = self.get_supplier(id)?;
+        let items: Vec<String> = self
+            .list_item()
+            .into_iter()
+            .filter(|item| {
+                item.offers.iter().any(|offer| offer.supplier_id == *id)
+            })
+            .map(|item| format!("{} ({})", item.name, item.id))
+            .collect();
+        if !items.is_empty() {
+            return Err(InventoryError::SupplierInUse {
+                supplier: *id,
+                items,
+            });
+        }
+        self.suppliers.shift_remove(id);
+        self.changes.push(Change::RemoveSupplier(supplier.clone()));
+        Ok(supplier)
+    }
+
+    /// Adds `offer` to the item `id`, replacing the one from the same
+    /// supplier. A new offer from the preferred supplier is recorded
+    /// as bought on `date`.
+    pub fn set_offer(
+        &mut self,
+        id: Uuid,
+        offer: Offer,
+        date: NaiveDate,
+    ) -> Result<()>
+    {
+        let supplier = self.get_supplier(&offer.supplier_id)?;
+        if !(offer.price.is_finite() && offer.price >= 0.0) {
+            return Err(InventoryError::InvalidPrice(offer.price));
+        }
+        let pack = offer.pack.value();
+        if !(pack.is_finite() && pack > 0.0) {
+            return Err(InventoryError::InvalidAmount(pack.to_string()));
+        }
+        let mut item = self.get_item(&id)?;
+        convert(&item, offer.pack, item.amount.kind())?;
+        let preferred = item.preferred_supplier == Some(supplier.id);
+        let changed = item.preferred_offer().is_none_or(|old| {
+            old.pack != offer.pack || old.price != offer.price
+        });
+        if preferred && changed {
+            buy(&mut item, Purchase::offered(&offer, &supplier, date))?;
+        }
+        match item
+            .offers
+            .iter_mut()
+            .find(|old| old.supplier_id == offer.supplier_id)
+        {
+            Some(old) => *old = offer,
+            None => item.offers.push(offer),
+        }
+        self.update_item(id, |mut old| *old = item)
+    }
+
+    /// Drops the offer of `supplier_id` for the item `id`, which stops
+    /// being preferred.
+    pub fn remove_offer(
+        &mut self,
+        id: Uuid,
+        supplier_id: Uuid,
+    ) -> Result<()>
+    {
+        let mut item = self.get_item(&id)?;
+        if !item.offers.iter().any(|o| o.supplier_id == supplier_id) {
+            return Err(InventoryError::OfferNotFound {
+                item: id,
+                supplier: supplier_id,
+            });
+        }
+        item.offers.retain(|offer| offer.supplier_id != supplier_id);
+        if item.preferred_supplier == Some(supplier_id) {
+            item.preferred_supplier = None;
+        }
+        self.update_item(id, |mut old| *old = item)
+    }
+
+    /// Buys the item `id` from `supplier_id` from `date` on, recording
+    /// its offer as a purchase then; `None` only forgets the choice,
+    /// leaving the price to the purchases made so far.
+    pub fn set_preferred_supplier(
+        &mut self,
+        id: Uuid,
+        supplier_id: Option<Uuid>,
+        date: NaiveDate,
+    ) -> Result<()>
+    {
+        let mut item = self.get_item(&id)?;
+        item.preferred_supplier = supplier_id;
+        if let Some(supplier_id) = supplier_id {
+            let Some(offer) = item.preferred_offer().cloned() else {
+                return Err(InventoryError::OfferNotFound {
+                    item: id,
+                    supplier: supplier_id,
+                });
+            };
+            let supplier = self.get_supplier(&supplier_id)?;
+            buy(&mut item, Purchase::offered(&offer, &supplier, date))?;
+        }
+        self.update_item(id, |mut old| *old = item)
+    }
+
+    /// Changes how the item `id` takes its price from its purchases.
+    pub fn set_price_policy(
+        &mut self,
+        id: Uuid,
+        policy: PricePolicy,
+    ) -> Result<()>
+    {
+        let mut item = self.get_item(&id)?;
+        item.price_policy = policy;
+        item.reprice()?;
+        self.update_item(id, |mut old| *old = item)
+    }
+
+    /// The offers for the item `id`, cheapest per kg, l or unit first.
+    pub fn compare_offers(&self, id: Uuid)
+        -> Result<Vec<OfferComparison>>
+    {
+        let item = self.get_item(&id)?;
+        let mut comparison = vec![];
+        for offer in item.offers.iter() {
+            let pack = convert(&item, offer.pack, item.amount.kind())?;
+            comparison.push(OfferComparison {
+                supplier: self.get_supplier(&offer.supplier_id)?,
+                offer: offer.clone(),
+                cost: NodeCost {
+                    amount: pack,
+                    price: offer.price,
+                },
+                preferred: item.preferred_supplier
+                    == Some(offer.supplier_id),
+            });
+        }
+        comparison.sort_by(|a, b| {
+            let unit_price = |c: &OfferComparison| {
+                c.cost.unit_price().unwrap_or(f64::INFINITY)
+            };
+            unit_price(a).total_cmp(&unit_price(b))
+        });
+        Ok(comparison)
+    }
+
+    /// The edge holding the portion of `component` in `source`, if any.
+    fn find_portion(
+        &self,
+        source: NodeIndex,
+        component: NodeIndex,
+    ) -> Option<EdgeIndex>
+    {
+        self.graph.find_edge(source, component)
+    }
+
+    pub fn get_portion(&self, from: Uuid, to: Uuid) -> Result<Portion>
+    {
+        let source = *self.get_node(&to)?;
+        let component = *self.get_node(&from)?;
+        let edge = self.find_portion(source, component).ok_or(
+            InventoryError::PortionNotFound {
+                recipe: to,
+                ingredient: from,
+            },
+        )?;
+        Ok(self.graph[edge].clone())
     }
 
     pub fn get_portions(&self, id: Uuid) -> Result<im::Vector<Portion>>
@@ -130,118 +621,1140 @@ impl Inventory
         let edges = self.graph.edges(*node);
         let portions: im::Vector<Portion> =
             edges.into_iter().map(|e| e.weight().clone()).collect();
-        // dbg!(portions.clone());
         Ok(portions)
     }
 
-    fn add_portion(&mut self, portion: Portion) -> Result<EdgeIndex>
+    /// Checks that `amount` of the ingredient at `component` can be used
+    /// in a recipe.
+    fn check_amount(
+        &self,
+        component: NodeIndex,
+        amount: Measure,
+    ) -> Result<()>
     {
-        // dbg!(portion.clone());
-        let source = self.get_node(&portion.source_id)?;
-        let component = self.get_node(&portion.component_id)?;
-        let index = self.graph.add_edge(*source, *component, portion);
-        Ok(index)
+        if !(amount.value.is_finite() && amount.value > 0.0) {
+            return Err(InventoryError::InvalidAmount(amount.to_string()));
+        }
+        let item = self.graph[component].borrow().clone();
+        convert(&item, amount.quantity(), item.amount.kind())?;
+        Ok(())
     }
 
+    /// Sets the amount of `from` in the recipe of `to`, adding the
+    /// portion if the recipe doesn't use the ingredient yet. The amount
+    /// may be in any kind the ingredient can be converted to.
     pub fn create_portion(
         &mut self,
         from: Uuid,
         to: Uuid,
-        amount: f64,
+        amount: Measure,
     ) -> Result<EdgeIndex>
     {
-        let source = self.get_node(&to)?;
-        let component = self.get_node(&from)?;
+        let source = *self.get_node(&to)?;
+        let component = *self.get_node(&from)?;
+        self.check_amount(component, amount)?;
+        if let Some(edge) = self.find_portion(source, component) {
+            self.set_portion_amount(edge, amount);
+            return Ok(edge);
+        }
+        if self.would_cycle(source, component) {
+            return Err(InventoryError::Cycle {
+                recipe: to,
+                ingredient: from,
+            });
+        }
         let portion = Portion::of(to, from, amount);
-        let index = self.graph.add_edge(*source, *component, portion);
+        self.changes.push(Change::AddPortion(portion.clone()));
+        let index = self.graph.add_edge(source, component, portion);
+        self.invalidate(source);
         Ok(index)
     }
 
-    pub fn remove_portion(&mut self, from: Uuid, to: Uuid) -> Result<()>
+    /// Changes the amount of an existing portion, returning the amount
+    /// it replaced.
+    pub fn update_portion(
+        &mut self,
+        from: Uuid,
+        to: Uuid,
+        amount: Measure,
+    ) -> Result<Measure>
     {
-        dbg!(self.graph.edge_count());
-        let source = self.get_node(&to)?;
-        let component = self.get_node(&from)?;
+        let source = *self.get_node(&to)?;
+        let component = *self.get_node(&from)?;
+        let edge = self.find_portion(source, component).ok_or(
+            InventoryError::PortionNotFound {
+                recipe: to,
+                ingredient: from,
+            },
+        )?;
+        self.check_amount(component, amount)?;
+        Ok(self.set_portion_amount(edge, amount))
+    }
 
-        let edge = self
-            .graph
-            .edges_connecting(*source, *component)
-            .into_iter()
-            .next()
-            .ok_or(anyhow!(format!(
-                "failed to retrieve edge index between {:?} and {:?}",
-                from, to
-            )))?;
-        // dbg!(edge.id());
-        let _removed_portion = self.graph.remove_edge(edge.id()).unwrap();
-        // dbg!(removed_portion);
-        dbg!(self.graph.edge_count());
+    /// Adds `amount` of `component` to the recipe at `source`, on top
+    /// of what the recipe already uses of it.
+    fn merge_portion(
+        &mut self,
+        source: NodeIndex,
+        component: NodeIndex,
+        amount: Measure,
+    ) -> Result<()>
+    {
+        if let Some(edge) = self.find_portion(source, component) {
+            let item = self.graph[component].borrow().clone();
+            let merged =
+                merge_amounts(&item, self.graph[edge].amount, amount)?;
+            self.set_portion_amount(edge, merged);
+            return Ok(());
+        }
+        let recipe_id = self.graph[source].borrow().id;
+        let component_id = self.graph[component].borrow().id;
+        let portion = Portion::of(recipe_id, component_id, amount);
+        self.changes.push(Change::AddPortion(portion.clone()));
+        self.graph.add_edge(source, component, portion);
+        self.invalidate(source);
+        Ok(())
+    }
+
+    /// Replaces the amount of the portion at `edge`, returning the old
+    /// one.
+    fn set_portion_amount(
+        &mut self,
+        edge: EdgeIndex,
+        amount: Measure,
+    ) -> Measure
+    {
+        let before = self.graph[edge].clone();
+        let previous = before.amount;
+        self.graph[edge].amount = amount;
+        let after = self.graph[edge].clone();
+        self.changes.push(Change::UpdatePortion { before, after });
+        let (source, _) = self.graph.edge_endpoints(edge).unwrap();
+        self.invalidate(source);
+        previous
+    }
+
+    pub fn remove_portion(&mut self, from: Uuid, to: Uuid) -> Result<()>
+    {
+        let source = *self.get_node(&to)?;
+        let component = *self.get_node(&from)?;
+        let edge = self.find_portion(source, component).ok_or(
+            InventoryError::PortionNotFound {
+                recipe: to,
+                ingredient: from,
+            },
+        )?;
+        if let Some(portion) = self.graph.remove_edge(edge) {
+            self.changes.push(Change::RemovePortion(portion));
+        }
+        self.invalidate(source);
         Ok(())
     }
 
-    pub fn test_portion(&mut self, from: Uuid, to: Uuid) -> Result<bool>
+    /// Whether a portion of `component` in `source` would make an item
+    /// an ingredient of itself.
+    fn would_cycle(&self, source: NodeIndex, component: NodeIndex)
+        -> bool
+    {
+        source == component
+            || has_path_connecting(&self.graph, component, source, None)
+    }
+
+    /// Items that can be added to the recipe of `id`: everything but
+    /// the recipe itself and the recipes that already use it.
+    pub fn eligible_components(&self, id: Uuid)
+        -> Result<im::Vector<Item>>
     {
-        let edge = self.create_portion(from, to, 1.0)?;
-        let result = is_cyclic_directed(&self.graph);
-        self.graph.remove_edge(edge);
-        Ok(result)
+        let node = *self.get_node(&id)?;
+        let reversed = Reversed(&self.graph);
+        let mut dfs = Dfs::new(reversed, node);
+        while dfs.next(reversed).is_some() {}
+        let eligible = self
+            .nodes
+            .values()
+            .filter(|index| !dfs.discovered.contains(index.index()))
+            .map(|index| self.graph[*index].borrow().clone())
+            .collect();
+        Ok(eligible)
     }
 
-    fn get_amount_(&self, index: NodeIndex) -> f64
+    /// Drops the cached cost of `index` and of every recipe that
+    /// reaches it.
+    fn invalidate(&self, index: NodeIndex)
     {
-        let mut total_amount = self.graph[index].borrow().amount.value;
+        let mut costs = self.costs.borrow_mut();
+        let reversed = Reversed(&self.graph);
+        let mut dfs = Dfs::new(reversed, index);
+        while let Some(node) = dfs.next(reversed) {
+            costs.remove(&self.graph[node].borrow().id);
+        }
+    }
+
+    /// Yield and price of one node, from the cached costs of its
+    /// components. Portions are converted through the component's
+    /// density or unit weight, and the yield is what is left after
+    /// trimming the item's own amount and cooking the whole recipe.
+    fn node_cost(
+        &self,
+        index: NodeIndex,
+        costs: &HashMap<Uuid, CostResult>,
+        date: Option<NaiveDate>,
+    ) -> CostResult
+    {
+        let item = self.graph[index].borrow();
+        let price = match date {
+            Some(date) => item.price_at(Some(date))?,
+            None => item.price,
+        };
+        if !price.is_finite() {
+            return Err(CostError::InvalidPrice {
+                item: item.id,
+                name: item.name.to_string(),
+            });
+        }
+        let kind = item.amount.kind();
+        let mut total_amount = item.trimmed_amount();
+        let mut total_price = price;
         for edge in self.graph.edges(index) {
-            let portion = edge.weight();
-            total_amount +=
-                portion.amount * self.get_amount_(edge.target());
+            let component = self.graph[edge.target()].borrow();
+            let component_cost = costs[&component.id].clone()?;
+            let unit_price =
+                self.unit_price_of(edge.target(), &component_cost)?;
+            let portion = edge.weight().amount.quantity();
+            total_amount += convert(&component, portion, kind)?.value();
+            let used =
+                convert(&component, portion, component.amount.kind())?;
+            total_price += used.value() * unit_price;
+        }
+        total_amount *= 1.0 - item.cooking_loss;
+        Ok(NodeCost {
+            amount: Quantity::of(kind, total_amount),
+            price: total_price,
+        })
+    }
+
+    /// Every node, components before the recipes that use them.
+    fn cost_order(&self) -> Result<Vec<NodeIndex>, CostError>
+    {
+        let order = toposort(&self.graph, None).map_err(|cycle| {
+            let item = self.graph[cycle.node_id()].borrow();
+            CostError::Cycle {
+                item: item.id,
+                name: item.name.to_string(),
+            }
+        })?;
+        Ok(order.into_iter().rev().collect())
+    }
+
+    /// Fills the cost of every uncached node in a single pass over the
+    /// graph, components before the recipes that use them.
+    fn evaluate(&self) -> Result<(), CostError>
+    {
+        if self.costs.borrow().len() == self.nodes.len() {
+            return Ok(());
+        }
+        let order = self.cost_order()?;
+        let mut costs = self.costs.borrow_mut();
+        for index in order {
+            let id = self.graph[index].borrow().id;
+            if !costs.contains_key(&id) {
+                let cost = self.node_cost(index, &costs, None);
+                costs.insert(id, cost);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn get_cost(&self, index: NodeIndex)
+        -> Result<NodeCost, CostError>
+    {
+        self.evaluate()?;
+        let id = self.graph[index].borrow().id;
+        let cost = self.costs.borrow()[&id].clone()?;
+        Ok(cost)
+    }
+
+    /// Cost of `index` with the prices paid up to `date`. Not cached.
+    pub fn get_cost_at(
+        &self,
+        index: NodeIndex,
+        date: NaiveDate,
+    ) -> Result<NodeCost, CostError>
+    {
+        let mut costs = HashMap::new();
+        for node in self.cost_order()? {
+            let id = self.graph[node].borrow().id;
+            let cost = self.node_cost(node, &costs, Some(date));
+            costs.insert(id, cost);
+            if node == index {
+                break;
+            }
         }
-        total_amount
+        let id = self.graph[index].borrow().id;
+        costs.remove(&id).unwrap()
+    }
+
+    fn unit_price_of(
+        &self,
+        index: NodeIndex,
+        cost: &NodeCost,
+    ) -> Result<f64, CostError>
+    {
+        cost.unit_price().ok_or_else(|| {
+            let item = self.graph[index].borrow();
+            CostError::ZeroYield {
+                item: item.id,
+                name: item.name.to_string(),
+            }
+        })
     }
 
-    fn get_price_(&self, index: NodeIndex) -> f64
+    /// The portion's amount in the kind its component is measured in.
+    pub fn get_portion_amount(&self, portion: &Portion)
+        -> Result<Quantity>
     {
-        let mut total_price = self.graph[index].borrow().price;
+        let item = self.get_item(&portion.component_id)?;
+        let amount =
+            convert(&item, portion.amount.quantity(), item.amount.kind())?;
+        Ok(amount)
+    }
+
+    fn breakdown_node(
+        &self,
+        index: NodeIndex,
+        share: f64,
+        total: f64,
+    ) -> Result<BreakdownNode, CostError>
+    {
+        let item = self.graph[index].borrow().clone();
+        let cost = self.get_cost(index)?;
+        let mut children = Vec::new();
         for edge in self.graph.edges(index) {
-            let portion = edge.weight();
-            total_price +=
-                portion.amount * self.get_unit_price(edge.target());
+            let component = self.graph[edge.target()].borrow().clone();
+            let component_cost = self.get_cost(edge.target())?;
+            self.unit_price_of(edge.target(), &component_cost)?;
+            let used = convert(
+                &component,
+                edge.weight().amount.quantity(),
+                component.amount.kind(),
+            )?;
+            let component_share =
+                share * used.value() / component_cost.amount.value();
+            children.push(self.breakdown_node(
+                edge.target(),
+                component_share,
+                total,
+            )?);
         }
-        total_price
+        let price = cost.price * share;
+        let percent = if total > 0.0 {
+            100.0 * price / total
+        } else {
+            0.0
+        };
+        Ok(BreakdownNode {
+            item_id: item.id,
+            name: item.name.to_string(),
+            amount: Quantity::of(
+                cost.amount.kind(),
+                cost.amount.value() * share,
+            ),
+            cost: price,
+            own_amount: Quantity::of(
+                item.amount.kind(),
+                item.amount.value() * share,
+            ),
+            own_cost: item.price * share,
+            percent,
+            children,
+        })
+    }
+
+    /// Cost of the whole yield of `id`, broken down through its portions
+    /// down to the raw ingredients.
+    pub fn get_breakdown(&self, id: Uuid) -> Result<BreakdownNode>
+    {
+        let node = *self.get_node(&id)?;
+        let total = self.get_cost(node)?.price;
+        Ok(self.breakdown_node(node, 1.0, total)?)
     }
 
     pub fn get_price(&self, id: Uuid) -> Result<f64>
     {
         let node = self.get_node(&id)?;
-        let price = self.get_price_(*node);
-        Ok(price)
+        Ok(self.get_cost(*node)?.price)
     }
 
-    pub fn get_amount(&self, id: Uuid) -> Result<f64>
+    /// Price of `id`, its recipe included, as it was on `date`.
+    pub fn get_price_at(&self, id: Uuid, date: NaiveDate) -> Result<f64>
     {
         let node = self.get_node(&id)?;
-        let amount = self.get_amount_(*node);
-        Ok(amount)
+        Ok(self.get_cost_at(*node, date)?.price)
     }
 
-    pub fn get_unit_price(&self, index: NodeIndex) -> f64
+    /// Price of `id` from `from` to `to`: a point on `from`, and one
+    /// on every day a purchase of the item or of an ingredient changed
+    /// it. The price holds until the next point.
+    pub fn get_price_series(
+        &self,
+        id: Uuid,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<Vec<CostPoint>>
     {
-        self.get_price_(index) / self.get_amount_(index)
+        let node = *self.get_node(&id)?;
+        let mut dates = vec![from];
+        let mut dfs = Dfs::new(&self.graph, node);
+        while let Some(index) = dfs.next(&self.graph) {
+            let item = self.graph[index].borrow();
+            dates.extend(
+                item.purchases
+                    .iter()
+                    .map(|purchase| purchase.date)
+                    .filter(|&date| from < date && date <= to),
+            );
+        }
+        dates.sort();
+        dates.dedup();
+        let mut series: Vec<CostPoint> = vec![];
+        for date in dates {
+            let price = self.get_cost_at(node, date)?.price;
+            if series.last().is_none_or(|last| last.price != price) {
+                series.push(CostPoint { date, price });
+            }
+        }
+        Ok(series)
+    }
+
+    pub fn get_amount(&self, id: Uuid) -> Result<Quantity>
+    {
+        let node = self.get_node(&id)?;
+        Ok(self.get_cost(*node)?.amount)
+    }
+
+    /// Price per base unit (kg, m³ or unit) of the item.
+    pub fn get_unit_price(
+        &self,
+        index: NodeIndex,
+    ) -> Result<f64, CostError>
+    {
+        let cost = self.get_cost(index)?;
+        self.unit_price_of(index, &cost)
     }
 }
 
-impl TryFrom<AppData> for Inventory
+/// Adds `purchase` to the history of `item`. Items that aren't bought
+/// in any amount, like most recipes, have nothing to scale it to.
+fn buy(item: &mut Item, purchase: Purchase) -> Result<()>
+{
+    if !item.is_bought() {
+        let amount = item.amount.value();
+        return Err(InventoryError::InvalidAmount(amount.to_string()));
+    }
+    check_purchase(&purchase)?;
+    item.add_purchase(purchase)?;
+    Ok(())
+}
+
+/// Fails unless `purchase` has a price and a quantity bought.
+fn check_purchase(purchase: &Purchase) -> Result<()>
 {
-    type Error = anyhow::Error;
+    if !(purchase.price.is_finite() && purchase.price >= 0.0) {
+        return Err(InventoryError::InvalidPrice(purchase.price));
+    }
+    let quantity = purchase.quantity.value();
+    if !(quantity.is_finite() && quantity > 0.0) {
+        return Err(InventoryError::InvalidAmount(quantity.to_string()));
+    }
+    Ok(())
+}
 
-    fn try_from(data: AppData) -> Result<Self>
+/// Adds two amounts of `item`, keeping the unit of the first one.
+fn merge_amounts(
+    item: &Item,
+    first: Measure,
+    second: Measure,
+) -> Result<Measure>
+{
+    if first.unit == second.unit {
+        return Ok(Measure::new(first.value + second.value, first.unit));
+    }
+    let second = convert(item, second.quantity(), first.unit.kind())?;
+    let per_unit = first.unit.quantity(1.0).value();
+    Ok(Measure::new(
+        first.value + second.value() / per_unit,
+        first.unit,
+    ))
+}
+
+impl Inventory
+{
+    /// Builds an inventory from `data`, reporting every problem in it.
+    /// Invalid values are reset, duplicate items keep their first
+    /// entry, offers from missing suppliers are dropped, and portions
+    /// that reference missing items, have invalid amounts or would
+    /// close a cycle are dropped.
+    pub fn repair(data: AppData) -> (Self, ValidationReport)
     {
         let mut inventory = Inventory::new();
-        for item in data.items.into_iter() {
-            inventory.add_item(item)?;
+        let mut report = ValidationReport::default();
+        let names: HashMap<Uuid, String> = data
+            .items
+            .iter()
+            .map(|item| (item.id, item.name.to_string()))
+            .collect();
+        let item_ref = |id: Uuid| ItemRef {
+            id,
+            name: names.get(&id).cloned(),
+        };
+
+        for supplier in data.suppliers.into_iter() {
+            inventory.suppliers.entry(supplier.id).or_insert(supplier);
+        }
+        for mut item in data.items.into_iter() {
+            if inventory.nodes.contains_key(&item.id) {
+                report.push(Problem::DuplicateItem(item_ref(item.id)));
+                continue;
+            }
+            for problem in sanitize_item(&mut item) {
+                report.push(problem);
+            }
+            for problem in sanitize_offers(&mut item, &inventory.suppliers)
+            {
+                report.push(problem);
+            }
+            let node = Rc::new(RefCell::new(item.clone()));
+            let index = inventory.graph.add_node(node);
+            inventory.nodes.insert(item.id, index);
         }
+
         for portion in data.portions.into_iter() {
-            inventory.add_portion(portion)?;
+            let recipe = item_ref(portion.source_id);
+            let ingredient = item_ref(portion.component_id);
+            let (source, component) = match (
+                inventory.nodes.get(&portion.source_id),
+                inventory.nodes.get(&portion.component_id),
+            ) {
+                (Some(&source), Some(&component)) => (source, component),
+                _ => {
+                    report.push(Problem::DanglingPortion {
+                        recipe,
+                        ingredient,
+                    });
+                    continue;
+                }
+            };
+            let amount = portion.amount;
+            if !(amount.value.is_finite() && amount.value > 0.0) {
+                report.push(Problem::InvalidPortionAmount {
+                    recipe,
+                    ingredient,
+                    amount: amount.to_string(),
+                });
+                continue;
+            }
+            if let Some(edge) = inventory.find_portion(source, component) {
+                let item = inventory.graph[component].borrow().clone();
+                let merged = merge_amounts(
+                    &item,
+                    inventory.graph[edge].amount,
+                    amount,
+                );
+                if let Ok(merged) = merged {
+                    inventory.graph[edge].amount = merged;
+                }
+                report.push(Problem::DuplicatePortion {
+                    recipe,
+                    ingredient,
+                    merged: merged.is_ok(),
+                });
+                continue;
+            }
+            if inventory.would_cycle(source, component) {
+                report.push(Problem::Cycle { recipe, ingredient });
+                continue;
+            }
+            inventory.graph.add_edge(source, component, portion);
+        }
+
+        (inventory, report)
+    }
+}
+
+/// Fails on damaged data; problems that are not fatal are fixed
+/// without a word, see `Inventory::repair` for a load that tells.
+impl TryFrom<AppData> for Inventory
+{
+    type Error = InventoryError;
+
+    fn try_from(data: AppData) -> Result<Self>
+    {
+        let (inventory, report) = Inventory::repair(data);
+        if report.is_fatal() {
+            return Err(InventoryError::Invalid(report));
         }
         Ok(inventory)
     }
 }
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::model::quantity::{QuantityKind, Unit};
+
+    /// A kilogram of `name` for `price`.
+    fn ingredient(name: &str, price: f64) -> Item
+    {
+        Item::builder()
+            .with_name(name)
+            .with_amount(Quantity::of(QuantityKind::Mass, 1.0))
+            .with_price(price)
+            .build()
+            .unwrap()
+    }
+
+    fn grams(value: f64) -> Measure { Measure::new(value, Unit::Gram) }
+
+    /// Cake made of dough and sugar, dough of flour and sugar; returns
+    /// the ids of flour, sugar, dough and cake.
+    fn bakery(inventory: &mut Inventory) -> [Uuid; 4]
+    {
+        let items = [
+            ingredient("flour", 5.0),
+            ingredient("sugar", 10.0),
+            Item::new("dough"),
+            Item::new("cake"),
+        ];
+        let ids = items.each_ref().map(|item| item.id);
+        for item in items {
+            inventory.add_item(item).unwrap();
+        }
+        let [flour, sugar, dough, cake] = ids;
+        inventory
+            .create_portion(flour, dough, grams(500.0))
+            .unwrap();
+        inventory
+            .create_portion(sugar, dough, grams(500.0))
+            .unwrap();
+        inventory.create_portion(dough, cake, grams(500.0)).unwrap();
+        inventory.create_portion(sugar, cake, grams(200.0)).unwrap();
+        ids
+    }
+
+    fn assert_price(inventory: &Inventory, id: Uuid, expected: f64)
+    {
+        let price = inventory.get_price(id).unwrap();
+        assert!(
+            (price - expected).abs() < 1e-9,
+            "{} != {}",
+            price,
+            expected
+        );
+    }
+
+    #[test]
+    fn recipes_follow_a_component_update()
+    {
+        let mut inventory = Inventory::new();
+        let [flour, _, dough, cake] = bakery(&mut inventory);
+        assert_price(&inventory, dough, 7.5);
+        assert_price(&inventory, cake, 5.75);
+
+        inventory
+            .update_item(flour, |mut item| item.price = 8.0)
+            .unwrap();
+        assert_price(&inventory, dough, 9.0);
+        assert_price(&inventory, cake, 6.5);
+    }
+
+    #[test]
+    fn removing_a_middle_recipe()
+    {
+        let mut inventory = Inventory::new();
+        let [flour, _, dough, cake] = bakery(&mut inventory);
+        assert_price(&inventory, cake, 5.75);
+
+        let refused = inventory.remove_item(&dough, RemovalPolicy::Refuse);
+        assert!(matches!(refused, Err(InventoryError::InUse { .. })));
+        assert_price(&inventory, cake, 5.75);
+
+        inventory
+            .remove_item(&dough, RemovalPolicy::Cascade)
+            .unwrap();
+        assert!(inventory.get_item(&dough).is_err());
+        assert_price(&inventory, cake, 2.0);
+        // the flour only went into the dough
+        inventory
+            .update_item(flour, |mut item| item.price = 8.0)
+            .unwrap();
+        assert_price(&inventory, cake, 2.0);
+    }
+
+    #[test]
+    fn adding_an_existing_id_fails()
+    {
+        let mut inventory = Inventory::new();
+        let [flour, _, _, cake] = bakery(&mut inventory);
+        let copy = Item {
+            id: flour,
+            ..ingredient("flour", 1.0)
+        };
+        let added = inventory.add_item(copy);
+        assert!(
+            matches!(added, Err(InventoryError::DuplicateItem(id)) if id == flour)
+        );
+        assert_eq!(inventory.list_item().len(), 4);
+        assert_price(&inventory, flour, 5.0);
+        assert_price(&inventory, cake, 5.75);
+    }
+
+    #[test]
+    fn repairing_reports_and_drops_bad_entries()
+    {
+        let (flour, sugar) =
+            (ingredient("flour", 5.0), ingredient("sugar", 1.0));
+        let (dough, ghost) = (Item::new("dough"), Uuid::new_v4());
+        let portion = |from: &Item, to: Uuid, value| Portion {
+            source_id: to,
+            component_id: from.id,
+            amount: grams(value),
+        };
+        let data = AppData {
+            portions: vec![
+                portion(&flour, dough.id, 300.0),
+                portion(&flour, dough.id, 200.0),
+                portion(&sugar, ghost, 10.0),
+                portion(&sugar, dough.id, -1.0),
+                portion(&dough, flour.id, 10.0),
+            ],
+            items: vec![
+                flour.clone(),
+                sugar,
+                dough.clone(),
+                flour.clone(),
+            ],
+            ..AppData::default()
+        };
+
+        let (inventory, report) = Inventory::repair(data);
+        let problems = &report.problems;
+        assert_eq!(problems.len(), 5, "{}", report);
+        assert!(matches!(problems[0], Problem::DuplicateItem(_)));
+        assert!(matches!(
+            problems[1],
+            Problem::DuplicatePortion { merged: true, .. }
+        ));
+        match &problems[2] {
+            Problem::DanglingPortion { recipe, .. } => {
+                assert_eq!(recipe.name, None)
+            }
+            problem => panic!("{}", problem),
+        }
+        assert!(matches!(
+            problems[3],
+            Problem::InvalidPortionAmount { .. }
+        ));
+        assert!(matches!(problems[4], Problem::Cycle { .. }));
+        assert!(report.is_fatal());
+
+        assert_eq!(inventory.list_item().len(), 3);
+        assert_eq!(inventory.get_all_portions().len(), 1);
+        assert_price(&inventory, dough.id, 2.5);
+    }
+
+    #[test]
+    fn purchases_need_an_amount_to_scale_to()
+    {
+        let mut inventory = Inventory::new();
+        let [flour, _, dough, _] = bakery(&mut inventory);
+        let purchase = |quantity| Purchase {
+            date: NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(),
+            supplier: String::new(),
+            quantity,
+            price: 6.0,
+        };
+        let kilo = Quantity::of(QuantityKind::Mass, 1.0);
+
+        let result = inventory.record_purchase(dough, purchase(kilo));
+        assert!(matches!(result, Err(InventoryError::InvalidAmount(_))));
+        assert!(inventory.get_item(&dough).unwrap().purchases.is_empty());
+        assert_price(&inventory, dough, 7.5);
+
+        inventory.record_purchase(flour, purchase(kilo)).unwrap();
+        assert_price(&inventory, flour, 6.0);
+        assert_price(&inventory, dough, 8.0);
+    }
+
+    #[test]
+    fn the_preferred_offer_is_bought()
+    {
+        let mut inventory = Inventory::new();
+        let [flour, _, dough, _] = bakery(&mut inventory);
+        let (mill, shop) = (Supplier::new("mill"), Supplier::new("shop"));
+        let (mill_id, shop_id) = (mill.id, shop.id);
+        inventory.add_supplier(mill).unwrap();
+        inventory.add_supplier(shop).unwrap();
+        let date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let offer = |supplier_id, kilos, price| Offer {
+            supplier_id,
+            pack: Quantity::of(QuantityKind::Mass, kilos),
+            price,
+            lead_time_days: 0,
+        };
+
+        inventory
+            .set_offer(flour, offer(shop_id, 0.5, 3.0), date)
+            .unwrap();
+        inventory
+            .set_offer(flour, offer(mill_id, 25.0, 100.0), date)
+            .unwrap();
+        // offers alone don't change the price
+        assert_price(&inventory, flour, 5.0);
+        let comparison = inventory.compare_offers(flour).unwrap();
+        let suppliers: Vec<_> =
+            comparison.iter().map(|c| c.supplier.id).collect();
+        assert_eq!(suppliers, [mill_id, shop_id]);
+        assert!(comparison.iter().all(|c| !c.preferred));
+
+        inventory
+            .set_preferred_supplier(flour, Some(mill_id), date)
+            .unwrap();
+        assert_price(&inventory, flour, 4.0);
+        assert_price(&inventory, dough, 7.0);
+        assert!(inventory.compare_offers(flour).unwrap()[0].preferred);
+
+        // a new price from the preferred supplier is bought, the same
+        // one again isn't
+        inventory
+            .set_offer(flour, offer(mill_id, 25.0, 75.0), date)
+            .unwrap();
+        assert_price(&inventory, flour, 3.0);
+        inventory
+            .set_offer(flour, offer(mill_id, 25.0, 75.0), date)
+            .unwrap();
+        let item = inventory.get_item(&flour).unwrap();
+        assert_eq!(item.purchases.len(), 2);
+
+        inventory.remove_offer(flour, mill_id).unwrap();
+        let item = inventory.get_item(&flour).unwrap();
+        assert_eq!(item.preferred_supplier, None);
+        assert_eq!(item.offers.len(), 1);
+        assert_price(&inventory, flour, 3.0);
+    }
+
+    #[test]
+    fn bad_offers_are_refused()
+    {
+        let mut inventory = Inventory::new();
+        let [flour, ..] = bakery(&mut inventory);
+        let mill = Supplier::new("mill");
+        let mill_id = mill.id;
+        inventory.add_supplier(mill).unwrap();
+        let date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let offer = |pack, price| Offer {
+            supplier_id: mill_id,
+            pack,
+            price,
+            lead_time_days: 0,
+        };
+        let kilo = Quantity::of(QuantityKind::Mass, 1.0);
+        let liter = Quantity::of(QuantityKind::Volume, 0.001);
+
+        let result =
+            inventory.set_offer(flour, offer(kilo, f64::NAN), date);
+        assert!(matches!(result, Err(InventoryError::InvalidPrice(_))));
+        let zero = Quantity::of(QuantityKind::Mass, 0.0);
+        let result = inventory.set_offer(flour, offer(zero, 2.0), date);
+        assert!(matches!(result, Err(InventoryError::InvalidAmount(_))));
+        // flour has no density to turn liters into kilograms
+        let result = inventory.set_offer(flour, offer(liter, 2.0), date);
+        assert!(matches!(result, Err(InventoryError::Conversion(_))));
+        let stranger = Offer {
+            supplier_id: Uuid::new_v4(),
+            ..offer(kilo, 2.0)
+        };
+        assert!(inventory.set_offer(flour, stranger, date).is_err());
+        let result =
+            inventory.set_preferred_supplier(flour, Some(mill_id), date);
+        assert!(matches!(
+            result,
+            Err(InventoryError::OfferNotFound { .. })
+        ));
+        assert!(inventory.get_item(&flour).unwrap().offers.is_empty());
+    }
+
+    #[test]
+    fn the_price_series_has_a_point_per_change()
+    {
+        let mut inventory = Inventory::new();
+        let [flour, sugar, dough, cake] = bakery(&mut inventory);
+        let day = |month, day| {
+            NaiveDate::from_ymd_opt(2024, month, day).unwrap()
+        };
+        let kilo = Quantity::of(QuantityKind::Mass, 1.0);
+        let purchases = [
+            (flour, day(2, 1), 6.0),
+            (flour, day(3, 1), 8.0),
+            (sugar, day(4, 1), 10.0),
+            (sugar, day(5, 1), 12.0),
+        ];
+        for (id, date, price) in purchases {
+            let purchase = Purchase {
+                date,
+                supplier: String::new(),
+                quantity: kilo,
+                price,
+            };
+            inventory.record_purchase(id, purchase).unwrap();
+        }
+
+        let series = |id| {
+            let points = inventory
+                .get_price_series(id, day(1, 1), day(4, 30))
+                .unwrap();
+            points
+                .iter()
+                .map(|point| (point.date, point.price))
+                .collect::<Vec<_>>()
+        };
+        // before its first purchase flour already costs 6, and sugar
+        // bought at the same price adds no point
+        assert_eq!(series(dough), [(day(1, 1), 8.0), (day(3, 1), 9.0)]);
+        assert_eq!(series(cake), [(day(1, 1), 6.0), (day(3, 1), 6.5)]);
+        assert!(inventory
+            .get_price_series(Uuid::new_v4(), day(1, 1), day(4, 30))
+            .is_err());
+    }
+
+    #[test]
+    fn the_breakdown_follows_every_portion()
+    {
+        let mut inventory = Inventory::new();
+        let [flour, sugar, dough, cake] = bakery(&mut inventory);
+
+        let tree = inventory.get_breakdown(cake).unwrap();
+        assert_eq!(tree.item_id, cake);
+        assert!((tree.cost - 5.75).abs() < 1e-9);
+        assert!((tree.amount.value() - 0.7).abs() < 1e-9);
+        assert!((tree.percent - 100.0).abs() < 1e-9);
+        let child = |node: &BreakdownNode, id| {
+            node.children
+                .iter()
+                .find(|c| c.item_id == id)
+                .cloned()
+                .unwrap()
+        };
+        assert_eq!(tree.children.len(), 2);
+        assert!((child(&tree, sugar).cost - 2.0).abs() < 1e-9);
+        // half of the dough goes into the cake
+        let half_dough = child(&tree, dough);
+        assert!((half_dough.cost - 3.75).abs() < 1e-9);
+        assert!((half_dough.amount.value() - 0.5).abs() < 1e-9);
+        assert!((half_dough.percent - 3.75 / 5.75 * 100.0).abs() < 1e-9);
+        assert!((child(&half_dough, flour).cost - 1.25).abs() < 1e-9);
+
+        let leaves = tree.leaves();
+        let ids: Vec<_> = leaves.iter().map(|line| line.item_id).collect();
+        assert_eq!(ids, [sugar, flour]);
+        let sugar_line = &leaves[0];
+        assert!((sugar_line.amount.value() - 0.45).abs() < 1e-9);
+        assert!((sugar_line.cost - 4.5).abs() < 1e-9);
+        let percents: f64 = leaves.iter().map(|line| line.percent).sum();
+        assert!((percents - 100.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn a_recipe_with_its_own_price_is_a_leaf_too()
+    {
+        let mut inventory = Inventory::new();
+        let [flour, _, dough, _] = bakery(&mut inventory);
+        inventory
+            .update_item(dough, |mut item| item.price = 2.5)
+            .unwrap();
+
+        let leaves = inventory.get_breakdown(dough).unwrap().leaves();
+        let line = leaves.iter().find(|l| l.item_id == dough).unwrap();
+        assert!((line.cost - 2.5).abs() < 1e-9);
+        assert!((line.percent - 25.0).abs() < 1e-9);
+        let flour = leaves.iter().find(|l| l.item_id == flour).unwrap();
+        assert!((flour.percent - 25.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn losses_shrink_the_yield_not_the_price()
+    {
+        let mut inventory = Inventory::new();
+        let mut potato = ingredient("potato", 4.0);
+        potato.trim_loss = 0.25;
+        let mut soup = Item::new("soup");
+        soup.cooking_loss = 0.5;
+        let (potato_id, soup_id) = (potato.id, soup.id);
+        inventory.add_item(potato).unwrap();
+        inventory.add_item(soup).unwrap();
+
+        // 4 buys 750 g of peeled potato
+        let potato_node = *inventory.get_node(&potato_id).unwrap();
+        let amount = inventory.get_amount(potato_id).unwrap();
+        assert!((amount.value() - 0.75).abs() < 1e-9);
+        assert_price(&inventory, potato_id, 4.0);
+        let unit_price = inventory.get_unit_price(potato_node).unwrap();
+        assert!((unit_price - 4.0 / 0.75).abs() < 1e-9);
+
+        // 300 g of peeled potato, half of it boiled away
+        inventory
+            .create_portion(potato_id, soup_id, grams(300.0))
+            .unwrap();
+        assert_price(&inventory, soup_id, 1.6);
+        let amount = inventory.get_amount(soup_id).unwrap();
+        assert!((amount.value() - 0.15).abs() < 1e-9);
+        let soup_node = *inventory.get_node(&soup_id).unwrap();
+        let unit_price = inventory.get_unit_price(soup_node).unwrap();
+        assert!((unit_price - 1.6 / 0.15).abs() < 1e-9);
+    }
+
+    #[test]
+    fn an_ingredient_without_yield_breaks_its_recipes()
+    {
+        let mut inventory = Inventory::new();
+        let [flour, _, dough, cake] = bakery(&mut inventory);
+        inventory
+            .update_item(flour, |mut item| {
+                item.amount = Quantity::of(QuantityKind::Mass, 0.0)
+            })
+            .unwrap();
+
+        for id in [dough, cake] {
+            match inventory.get_price(id) {
+                Err(InventoryError::Cost(e)) => {
+                    assert!(matches!(e, CostError::ZeroYield { .. }));
+                    assert_eq!(e.item(), (flour, "flour"));
+                }
+                result => panic!("{:?}", result),
+            }
+        }
+        assert!(inventory.get_breakdown(cake).is_err());
+        // flour alone still has a price, but none per kilogram
+        assert_price(&inventory, flour, 5.0);
+        let node = *inventory.get_node(&flour).unwrap();
+        assert!(inventory.get_unit_price(node).is_err());
+    }
+
+    #[test]
+    fn an_invalid_price_is_named()
+    {
+        let mut inventory = Inventory::new();
+        let [_, sugar, _, cake] = bakery(&mut inventory);
+        inventory
+            .update_item(sugar, |mut item| item.price = f64::NAN)
+            .unwrap();
+        match inventory.get_price(cake) {
+            Err(InventoryError::Cost(CostError::InvalidPrice {
+                item,
+                ..
+            })) => assert_eq!(item, sugar),
+            result => panic!("{:?}", result),
+        }
+    }
+
+    #[test]
+    fn portions_that_close_a_cycle_are_refused()
+    {
+        let mut inventory = Inventory::new();
+        let [flour, _, dough, cake] = bakery(&mut inventory);
+        let changes = inventory.changes().len();
+
+        for (from, to) in [(cake, flour), (cake, dough), (dough, dough)] {
+            let result = inventory.create_portion(from, to, grams(10.0));
+            assert!(
+                matches!(result, Err(InventoryError::Cycle { .. })),
+                "{:?}",
+                result
+            );
+        }
+        assert_eq!(inventory.changes().len(), changes);
+        assert_eq!(inventory.get_all_portions().len(), 4);
+        assert_price(&inventory, cake, 5.75);
+    }
+
+    #[test]
+    fn eligible_components_leave_out_the_recipes_above()
+    {
+        let mut inventory = Inventory::new();
+        let [flour, sugar, dough, cake] = bakery(&mut inventory);
+        let eligible = |id| {
+            let mut ids: Vec<Uuid> = inventory
+                .eligible_components(id)
+                .unwrap()
+                .iter()
+                .map(|item| item.id)
+                .collect();
+            ids.sort();
+            ids
+        };
+        let sorted = |mut ids: Vec<Uuid>| {
+            ids.sort();
+            ids
+        };
+
+        assert_eq!(eligible(cake), sorted(vec![flour, sugar, dough]));
+        assert_eq!(eligible(dough), sorted(vec![flour, sugar]));
+        assert_eq!(eligible(flour), [sugar]);
+        assert!(inventory.eligible_components(Uuid::new_v4()).is_err());
+    }
+
+    #[test]
+    fn adding_a_portion_twice_updates_it()
+    {
+        let mut inventory = Inventory::new();
+        let [flour, sugar, dough, _] = bakery(&mut inventory);
+        let changes = inventory.changes().len();
+
+        inventory
+            .create_portion(flour, dough, grams(300.0))
+            .unwrap();
+        assert_eq!(inventory.get_portions(dough).unwrap().len(), 2);
+        assert_eq!(
+            inventory.get_portion(flour, dough).unwrap().amount,
+            grams(300.0)
+        );
+        assert!(matches!(
+            inventory.changes()[changes..],
+            [Change::UpdatePortion { .. }]
+        ));
+
+        let previous = inventory
+            .update_portion(sugar, dough, grams(100.0))
+            .unwrap();
+        assert_eq!(previous, grams(500.0));
+        assert_price(&inventory, dough, 2.5);
+
+        inventory.remove_portion(sugar, dough).unwrap();
+        let result = inventory.update_portion(sugar, dough, grams(1.0));
+        assert!(matches!(
+            result,
+            Err(InventoryError::PortionNotFound { .. })
+        ));
+        assert!(inventory.remove_portion(sugar, dough).is_err());
+    }
+
+    #[test]
+    fn duplicate_portions_in_a_file_are_merged()
+    {
+        let mut inventory = Inventory::new();
+        let [flour, _, dough, _] = bakery(&mut inventory);
+        let mut data = AppData::from(inventory);
+        data.portions.push(Portion {
+            source_id: dough,
+            component_id: flour,
+            amount: Measure::new(0.25, Unit::Kilogram),
+        });
+
+        let inventory = Inventory::try_from(data).unwrap();
+        assert_eq!(
+            inventory.get_portion(flour, dough).unwrap().amount,
+            grams(750.0)
+        );
+        assert_price(&inventory, dough, 8.75);
+    }
+}
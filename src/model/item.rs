@@ -1,15 +1,29 @@
+use std::slice;
+
 use anyhow::Result;
+use chrono::NaiveDate;
 use serde::{Deserialize, Serialize};
-use uom::si::f64::Mass;
+use uom::si::f64::{Mass, MassDensity};
 use uuid::Uuid;
 
+use super::{
+    conversion::{convert, ConversionError},
+    price::{PricePolicy, Purchase},
+    quantity::Quantity,
+    supplier::Offer,
+};
+
 #[derive(Default)]
 pub struct ItemBuilder
 {
     id: Option<Uuid>,
     name: Option<String>,
-    amount: Option<Mass>,
+    amount: Option<Quantity>,
     price: Option<f64>,
+    density: Option<MassDensity>,
+    unit_mass: Option<Mass>,
+    trim_loss: Option<f64>,
+    cooking_loss: Option<f64>,
 }
 
 impl ItemBuilder
@@ -25,6 +39,14 @@ impl ItemBuilder
             name,
             amount,
             price,
+            density: self.density,
+            unit_mass: self.unit_mass,
+            trim_loss: self.trim_loss.unwrap_or_default(),
+            cooking_loss: self.cooking_loss.unwrap_or_default(),
+            purchases: vec![],
+            price_policy: PricePolicy::default(),
+            offers: vec![],
+            preferred_supplier: None,
         };
         Ok(item)
     }
@@ -38,10 +60,7 @@ impl ItemBuilder
         self.name = Some(String::from(new_name));
         self
     }
-    pub fn with_amount(
-        &mut self,
-        new_amount: Mass,
-    ) -> &mut Self
+    pub fn with_amount(&mut self, new_amount: Quantity) -> &mut Self
     {
         self.amount = Some(new_amount);
         self
@@ -51,6 +70,27 @@ impl ItemBuilder
         self.price = Some(new_price);
         self
     }
+    pub fn with_density(&mut self, new_density: MassDensity) -> &mut Self
+    {
+        self.density = Some(new_density);
+        self
+    }
+    pub fn with_unit_mass(&mut self, new_unit_mass: Mass) -> &mut Self
+    {
+        self.unit_mass = Some(new_unit_mass);
+        self
+    }
+    pub fn with_trim_loss(&mut self, new_trim_loss: f64) -> &mut Self
+    {
+        self.trim_loss = Some(new_trim_loss);
+        self
+    }
+    pub fn with_cooking_loss(&mut self, new_cooking_loss: f64)
+        -> &mut Self
+    {
+        self.cooking_loss = Some(new_cooking_loss);
+        self
+    }
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -58,15 +98,234 @@ pub struct Item
 {
     pub id: Uuid,
     pub name: String,
-    pub amount: Mass,
+    pub amount: Quantity,
     pub price: f64,
+    /// Used to convert between mass and volume.
+    #[serde(default)]
+    pub density: Option<MassDensity>,
+    /// Mass of one unit, used to convert counts.
+    #[serde(default)]
+    pub unit_mass: Option<Mass>,
+    /// Fraction of the bought amount lost to trimming (peels, bones).
+    #[serde(default)]
+    pub trim_loss: f64,
+    /// Fraction of the finished recipe lost while cooking.
+    #[serde(default)]
+    pub cooking_loss: f64,
+    /// What was paid for the item, oldest first. Once there is any,
+    /// `price` is derived from them.
+    #[serde(default)]
+    pub purchases: Vec<Purchase>,
+    #[serde(default)]
+    pub price_policy: PricePolicy,
+    /// What each supplier asks for the item.
+    #[serde(default)]
+    pub offers: Vec<Offer>,
+    /// Supplier the item is bought from; its offer is recorded as a
+    /// purchase when chosen and whenever it changes.
+    #[serde(default)]
+    pub preferred_supplier: Option<Uuid>,
 }
 
 impl Item
 {
     pub fn builder() -> ItemBuilder { ItemBuilder::default() }
+    /// Usable part of the item's own amount, after trimming.
+    pub fn trimmed_amount(&self) -> f64
+    {
+        self.amount.value() * (1.0 - self.trim_loss)
+    }
     pub fn new(name: &str) -> Self
     {
         Self::builder().with_name(name).build().unwrap()
     }
+
+    /// Whether some amount of the item is bought, so that its price can
+    /// be kept as purchases; recipes are usually made from nothing.
+    pub fn is_bought(&self) -> bool { self.amount.value() > 0.0 }
+
+    /// Price of the item's amount following `price_policy`, from the
+    /// purchases made up to `date`, or all of them. Dates before the
+    /// first purchase use it; without purchases, this is `price`.
+    pub fn price_at(
+        &self,
+        date: Option<NaiveDate>,
+    ) -> Result<f64, ConversionError>
+    {
+        let Some(first) = self.purchases.first() else {
+            return Ok(self.price);
+        };
+        let known = match date {
+            Some(date) => {
+                self.purchases.partition_point(|p| p.date <= date)
+            }
+            None => self.purchases.len(),
+        };
+        let known = match known {
+            0 => slice::from_ref(first),
+            known => &self.purchases[..known],
+        };
+        let used = match self.price_policy {
+            PricePolicy::Latest => &known[known.len() - 1..],
+            PricePolicy::Average { purchases: 0 } => known,
+            PricePolicy::Average { purchases } => {
+                &known[known.len().saturating_sub(purchases)..]
+            }
+        };
+        let mut quantity = 0.0;
+        let mut price = 0.0;
+        for purchase in used.iter() {
+            let bought =
+                convert(self, purchase.quantity, self.amount.kind())?;
+            quantity += bought.value();
+            price += purchase.price;
+        }
+        // a single purchase of the amount gives its price back exactly
+        Ok(price * (self.amount.value() / quantity))
+    }
+
+    /// The offer of the preferred supplier, if there is one.
+    pub fn preferred_offer(&self) -> Option<&Offer>
+    {
+        let supplier_id = self.preferred_supplier?;
+        self.offers
+            .iter()
+            .find(|offer| offer.supplier_id == supplier_id)
+    }
+
+    /// Sets `price` to the one the purchases give today.
+    pub fn reprice(&mut self) -> Result<(), ConversionError>
+    {
+        self.price = self.price_at(None)?;
+        Ok(())
+    }
+
+    /// Adds `purchase` to the history, after those made the same day,
+    /// and derives the price again. A purchase that can't be converted
+    /// to the item's amount is left out.
+    pub fn add_purchase(
+        &mut self,
+        purchase: Purchase,
+    ) -> Result<(), ConversionError>
+    {
+        let index =
+            self.purchases.partition_point(|p| p.date <= purchase.date);
+        self.purchases.insert(index, purchase);
+        if let Err(e) = self.reprice() {
+            self.purchases.remove(index);
+            return Err(e);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::model::quantity::QuantityKind;
+
+    fn day(day: u32) -> NaiveDate
+    {
+        NaiveDate::from_ymd_opt(2024, 5, day).unwrap()
+    }
+
+    fn kilos(value: f64) -> Quantity
+    {
+        Quantity::of(QuantityKind::Mass, value)
+    }
+
+    /// A kilogram of rice bought for 4 on the 1st, 2 kg for 10 on the
+    /// 10th and 500 g for 3.5 on the 20th.
+    fn rice() -> Item
+    {
+        let mut item = Item::builder()
+            .with_name("rice")
+            .with_amount(kilos(1.0))
+            .with_price(4.0)
+            .build()
+            .unwrap();
+        for (date, quantity, price) in [
+            (day(10), 2.0, 10.0),
+            (day(1), 1.0, 4.0),
+            (day(20), 0.5, 3.5),
+        ] {
+            item.add_purchase(Purchase {
+                date,
+                supplier: String::new(),
+                quantity: kilos(quantity),
+                price,
+            })
+            .unwrap();
+        }
+        item
+    }
+
+    fn assert_close(actual: f64, expected: f64)
+    {
+        assert!((actual - expected).abs() < 1e-9, "{}", actual);
+    }
+
+    #[test]
+    fn purchases_are_kept_in_date_order()
+    {
+        let item = rice();
+        let dates: Vec<_> =
+            item.purchases.iter().map(|p| p.date).collect();
+        assert_eq!(dates, [day(1), day(10), day(20)]);
+        assert_close(item.price, 7.0);
+    }
+
+    #[test]
+    fn latest_uses_the_last_purchase_up_to_the_date()
+    {
+        let item = rice();
+        assert_close(item.price_at(None).unwrap(), 7.0);
+        assert_close(item.price_at(Some(day(15))).unwrap(), 5.0);
+        assert_close(item.price_at(Some(day(10))).unwrap(), 5.0);
+        // before the first purchase, the first one is used
+        let april = NaiveDate::from_ymd_opt(2024, 4, 1).unwrap();
+        assert_close(item.price_at(Some(april)).unwrap(), 4.0);
+    }
+
+    #[test]
+    fn averages_are_weighted_by_quantity()
+    {
+        let mut item = rice();
+        item.price_policy = PricePolicy::Average { purchases: 0 };
+        // 17.5 for 3.5 kg
+        assert_close(item.price_at(None).unwrap(), 5.0);
+        assert_close(item.price_at(Some(day(15))).unwrap(), 14.0 / 3.0);
+        item.price_policy = PricePolicy::Average { purchases: 2 };
+        // 13.5 for 2.5 kg
+        assert_close(item.price_at(None).unwrap(), 5.4);
+        item.price_policy = PricePolicy::Average { purchases: 5 };
+        assert_close(item.price_at(None).unwrap(), 5.0);
+    }
+
+    #[test]
+    fn without_purchases_the_price_stands()
+    {
+        let mut item = rice();
+        item.purchases.clear();
+        item.price = 4.0;
+        item.price_policy = PricePolicy::Average { purchases: 0 };
+        assert_close(item.price_at(Some(day(1))).unwrap(), 4.0);
+    }
+
+    #[test]
+    fn unconvertible_purchases_are_refused()
+    {
+        let mut item = rice();
+        let purchases = item.purchases.clone();
+        let error = item.add_purchase(Purchase {
+            date: day(21),
+            supplier: String::new(),
+            quantity: Quantity::of(QuantityKind::Count, 3.0),
+            price: 2.0,
+        });
+        assert!(error.is_err());
+        assert_eq!(item.purchases, purchases);
+        assert_close(item.price, 7.0);
+    }
 }
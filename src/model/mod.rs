@@ -1,3 +1,11 @@
+pub mod change;
+pub mod conversion;
+pub mod cost;
+pub mod error;
 pub mod inventory;
 pub mod item;
 pub mod portion;
+pub mod price;
+pub mod quantity;
+pub mod supplier;
+pub mod validation;
@@ -1,12 +1,14 @@
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+use super::quantity::Measure;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Portion
 {
     pub source_id: Uuid,
     pub component_id: Uuid,
-    pub amount: f64,
+    pub amount: Measure,
 }
 
 impl Portion
@@ -14,7 +16,7 @@ impl Portion
     pub fn of(
         source_id: Uuid,
         component_id: Uuid,
-        amount: f64,
+        amount: Measure,
     ) -> Self
     {
         Self {
@@ -0,0 +1,124 @@
+use std::{fmt, str::FromStr};
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    item::Item,
+    quantity::Quantity,
+    supplier::{Offer, Supplier},
+};
+
+/// Something paid for an item.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Purchase
+{
+    pub date: NaiveDate,
+    #[serde(default)]
+    pub supplier: String,
+    /// Amount bought, in any kind the item converts to.
+    pub quantity: Quantity,
+    /// Price paid for the whole quantity.
+    pub price: f64,
+}
+
+impl Purchase
+{
+    /// The item's own amount bought at its current price.
+    pub fn current(item: &Item, date: NaiveDate, supplier: &str) -> Self
+    {
+        Self {
+            date,
+            supplier: supplier.to_string(),
+            quantity: item.amount,
+            price: item.price,
+        }
+    }
+
+    /// A pack bought from `supplier` at its `offer`.
+    pub fn offered(
+        offer: &Offer,
+        supplier: &Supplier,
+        date: NaiveDate,
+    ) -> Self
+    {
+        Self {
+            date,
+            supplier: supplier.name.clone(),
+            quantity: offer.pack,
+            price: offer.price,
+        }
+    }
+}
+
+/// How the price of an item follows its purchases.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "policy", rename_all = "snake_case")]
+pub enum PricePolicy
+{
+    /// What was paid the last time.
+    #[default]
+    Latest,
+    /// Average over the last `purchases`, weighted by quantity; all of
+    /// them when zero.
+    Average
+    {
+        purchases: usize,
+    },
+}
+
+impl fmt::Display for PricePolicy
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        match self {
+            PricePolicy::Latest => write!(f, "latest"),
+            PricePolicy::Average { purchases: 0 } => write!(f, "average"),
+            PricePolicy::Average { purchases } => {
+                write!(f, "average:{}", purchases)
+            }
+        }
+    }
+}
+
+impl FromStr for PricePolicy
+{
+    type Err = String;
+
+    /// Parses `latest`, `average` or `average:N`.
+    fn from_str(text: &str) -> Result<Self, Self::Err>
+    {
+        match text.split_once(':') {
+            None if text == "latest" => Ok(PricePolicy::Latest),
+            None if text == "average" => {
+                Ok(PricePolicy::Average { purchases: 0 })
+            }
+            Some(("average", count)) => count
+                .parse()
+                .map(|purchases| PricePolicy::Average { purchases })
+                .map_err(|e| format!("{}: {}", count, e)),
+            _ => Err(format!("{} is not a price policy", text)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn policies_parse_what_they_print()
+    {
+        for policy in [
+            PricePolicy::Latest,
+            PricePolicy::Average { purchases: 0 },
+            PricePolicy::Average { purchases: 3 },
+        ] {
+            assert_eq!(policy.to_string().parse(), Ok(policy));
+        }
+        assert!("average:".parse::<PricePolicy>().is_err());
+        assert!("cheapest".parse::<PricePolicy>().is_err());
+    }
+}
@@ -0,0 +1,182 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+use uom::si::{
+    f64::{Mass, Volume},
+    mass::{gram, kilogram},
+    volume::{cubic_meter, liter, milliliter},
+};
+
+use super::conversion::{CUP, TABLESPOON, TEASPOON};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum QuantityKind
+{
+    Mass,
+    Volume,
+    Count,
+}
+
+/// An amount of something, in one of the dimensions an item can be
+/// bought in. Counts are plain numbers of units.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Quantity
+{
+    Mass(Mass),
+    Volume(Volume),
+    Count(f64),
+}
+
+impl Default for Quantity
+{
+    fn default() -> Self { Quantity::Mass(Mass::default()) }
+}
+
+impl Quantity
+{
+    /// Builds a quantity from a value in the base unit of `kind`.
+    pub fn of(kind: QuantityKind, value: f64) -> Self
+    {
+        match kind {
+            QuantityKind::Mass => {
+                Quantity::Mass(Mass::new::<kilogram>(value))
+            }
+            QuantityKind::Volume => {
+                Quantity::Volume(Volume::new::<cubic_meter>(value))
+            }
+            QuantityKind::Count => Quantity::Count(value),
+        }
+    }
+
+    pub fn kind(&self) -> QuantityKind
+    {
+        match self {
+            Quantity::Mass(_) => QuantityKind::Mass,
+            Quantity::Volume(_) => QuantityKind::Volume,
+            Quantity::Count(_) => QuantityKind::Count,
+        }
+    }
+
+    /// Value in the base unit of the kind (kg, m³ or units).
+    pub fn value(&self) -> f64
+    {
+        match self {
+            Quantity::Mass(mass) => mass.value,
+            Quantity::Volume(volume) => volume.value,
+            Quantity::Count(count) => *count,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Unit
+{
+    Gram,
+    #[default]
+    Kilogram,
+    Milliliter,
+    Liter,
+    Cup,
+    Tablespoon,
+    Teaspoon,
+    Piece,
+    Dozen,
+}
+
+impl Unit
+{
+    pub fn parse(text: &str) -> Option<Self>
+    {
+        match text {
+            "g" | "grama" | "gramas" => Some(Unit::Gram),
+            "kg" | "kilo" | "kilos" => Some(Unit::Kilogram),
+            "ml" | "mililitro" | "mililitros" => Some(Unit::Milliliter),
+            "l" | "litro" | "litros" => Some(Unit::Liter),
+            "xícara" | "xícaras" | "xic" | "cup" => Some(Unit::Cup),
+            "colher de sopa" | "colheres de sopa" | "cs" | "tbsp" => {
+                Some(Unit::Tablespoon)
+            }
+            "colher de chá" | "colheres de chá" | "cc" | "tsp" => {
+                Some(Unit::Teaspoon)
+            }
+            "un" | "unidade" | "unidades" | "lata" | "latas" => {
+                Some(Unit::Piece)
+            }
+            "dz" | "dúzia" | "dúzias" => Some(Unit::Dozen),
+            _ => None,
+        }
+    }
+
+    pub fn symbol(&self) -> &'static str
+    {
+        match self {
+            Unit::Gram => "g",
+            Unit::Kilogram => "kg",
+            Unit::Milliliter => "ml",
+            Unit::Liter => "l",
+            Unit::Cup => "xícara",
+            Unit::Tablespoon => "colher de sopa",
+            Unit::Teaspoon => "colher de chá",
+            Unit::Piece => "un",
+            Unit::Dozen => "dz",
+        }
+    }
+
+    pub fn kind(&self) -> QuantityKind
+    {
+        match self {
+            Unit::Gram | Unit::Kilogram => QuantityKind::Mass,
+            Unit::Milliliter
+            | Unit::Liter
+            | Unit::Cup
+            | Unit::Tablespoon
+            | Unit::Teaspoon => QuantityKind::Volume,
+            Unit::Piece | Unit::Dozen => QuantityKind::Count,
+        }
+    }
+
+    pub fn quantity(&self, value: f64) -> Quantity
+    {
+        match self {
+            Unit::Gram => Quantity::Mass(Mass::new::<gram>(value)),
+            Unit::Kilogram => Quantity::Mass(Mass::new::<kilogram>(value)),
+            Unit::Milliliter => {
+                Quantity::Volume(Volume::new::<milliliter>(value))
+            }
+            Unit::Liter => Quantity::Volume(Volume::new::<liter>(value)),
+            Unit::Cup => Unit::Milliliter.quantity(value * CUP),
+            Unit::Tablespoon => {
+                Unit::Milliliter.quantity(value * TABLESPOON)
+            }
+            Unit::Teaspoon => Unit::Milliliter.quantity(value * TEASPOON),
+            Unit::Piece => Quantity::Count(value),
+            Unit::Dozen => Quantity::Count(value * 12.0),
+        }
+    }
+}
+
+/// An amount as it was entered, keeping its unit.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Measure
+{
+    pub value: f64,
+    pub unit: Unit,
+}
+
+impl Measure
+{
+    pub fn new(value: f64, unit: Unit) -> Self { Self { value, unit } }
+
+    pub fn quantity(&self) -> Quantity { self.unit.quantity(self.value) }
+}
+
+impl fmt::Display for Measure
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        write!(f, "{} {}", self.value, self.unit.symbol())
+    }
+}
@@ -0,0 +1,54 @@
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::{cost::NodeCost, quantity::Quantity};
+
+/// A vendor items are bought from.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Supplier
+{
+    pub id: Uuid,
+    pub name: String,
+    /// Phone, e-mail or whatever reaches them.
+    #[serde(default)]
+    pub contact: String,
+}
+
+impl Supplier
+{
+    pub fn new(name: &str) -> Self
+    {
+        Self {
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+            contact: String::new(),
+        }
+    }
+}
+
+/// What a supplier asks for a pack of an item. An item has at most one
+/// offer per supplier.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Offer
+{
+    pub supplier_id: Uuid,
+    /// Amount sold together, in any kind the item converts to.
+    pub pack: Quantity,
+    /// Price of the whole pack.
+    pub price: f64,
+    /// Days between ordering and delivery.
+    #[serde(default)]
+    pub lead_time_days: u32,
+}
+
+/// An offer next to the others for the same item, as the comparison
+/// of suppliers shows it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct OfferComparison
+{
+    pub supplier: Supplier,
+    pub offer: Offer,
+    /// The pack in the kind of the item's amount, with its price.
+    pub cost: NodeCost,
+    pub preferred: bool,
+}
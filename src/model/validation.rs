@@ -0,0 +1,349 @@
+use std::fmt;
+
+use chrono::NaiveDate;
+use indexmap::IndexMap;
+use thiserror::Error;
+use uuid::Uuid;
+
+use super::{item::Item, quantity::Quantity, supplier::Supplier};
+
+/// An item as named in a problem report. The name is missing when the
+/// id doesn't belong to any item in the file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ItemRef
+{
+    pub id: Uuid,
+    pub name: Option<String>,
+}
+
+impl fmt::Display for ItemRef
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        match &self.name {
+            Some(name) => write!(f, "{} ({})", name, self.id),
+            None => write!(f, "unknown item ({})", self.id),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Error)]
+pub enum Problem
+{
+    #[error("{0} appears more than once")]
+    DuplicateItem(ItemRef),
+    #[error("{item} has an invalid price: {price}")]
+    InvalidPrice
+    {
+        item: ItemRef,
+        price: f64,
+    },
+    #[error("{item} has an invalid amount: {amount}")]
+    InvalidAmount
+    {
+        item: ItemRef,
+        amount: f64,
+    },
+    #[error("{item} has an invalid purchase on {date}")]
+    InvalidPurchase
+    {
+        item: ItemRef,
+        date: NaiveDate,
+    },
+    #[error("{item} has an invalid offer from {supplier}")]
+    InvalidOffer
+    {
+        item: ItemRef,
+        supplier: Uuid,
+    },
+    #[error("{item} has an invalid density: {density} kg/m³")]
+    InvalidDensity
+    {
+        item: ItemRef,
+        density: f64,
+    },
+    #[error("{item} has an invalid unit weight: {mass} kg")]
+    InvalidUnitMass
+    {
+        item: ItemRef,
+        mass: f64,
+    },
+    #[error("{item} has an invalid loss: {loss}")]
+    InvalidLoss
+    {
+        item: ItemRef,
+        loss: f64,
+    },
+    #[error(
+        "portion of {ingredient} in {recipe} references a missing item"
+    )]
+    DanglingPortion
+    {
+        recipe: ItemRef,
+        ingredient: ItemRef,
+    },
+    #[error(
+        "portion of {ingredient} in {recipe} has an invalid amount: \
+         {amount}"
+    )]
+    InvalidPortionAmount
+    {
+        recipe: ItemRef,
+        ingredient: ItemRef,
+        amount: String,
+    },
+    #[error("portion of {ingredient} in {recipe} closes a cycle")]
+    Cycle
+    {
+        recipe: ItemRef,
+        ingredient: ItemRef,
+    },
+    #[error(
+        "{ingredient} appears more than once in {recipe}{}",
+        if *.merged { " (merged)" } else { "" }
+    )]
+    DuplicatePortion
+    {
+        recipe: ItemRef,
+        ingredient: ItemRef,
+        merged: bool,
+    },
+}
+
+impl Problem
+{
+    /// Whether the file can't be opened as is. Duplicate portions that
+    /// could be merged don't lose any data.
+    pub fn is_fatal(&self) -> bool
+    {
+        !matches!(self, Problem::DuplicatePortion { merged: true, .. })
+    }
+}
+
+/// Every problem found while loading a data file, in file order.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ValidationReport
+{
+    pub problems: Vec<Problem>,
+}
+
+impl ValidationReport
+{
+    pub fn is_empty(&self) -> bool { self.problems.is_empty() }
+
+    pub fn is_fatal(&self) -> bool
+    {
+        self.problems.iter().any(Problem::is_fatal)
+    }
+
+    pub fn push(&mut self, problem: Problem)
+    {
+        self.problems.push(problem)
+    }
+}
+
+impl fmt::Display for ValidationReport
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        for problem in self.problems.iter() {
+            writeln!(f, "- {}", problem)?;
+        }
+        Ok(())
+    }
+}
+
+/// Drops the offers of `item` from suppliers not in `suppliers` or
+/// with invalid values, reporting each one.
+pub fn sanitize_offers(
+    item: &mut Item,
+    suppliers: &IndexMap<Uuid, Supplier>,
+) -> Vec<Problem>
+{
+    let item_ref = ItemRef {
+        id: item.id,
+        name: Some(item.name.to_string()),
+    };
+    let mut problems = vec![];
+    item.offers.retain(|offer| {
+        let pack = offer.pack.value();
+        let valid = suppliers.contains_key(&offer.supplier_id)
+            && offer.price.is_finite()
+            && offer.price >= 0.0
+            && pack.is_finite()
+            && pack > 0.0;
+        if !valid {
+            problems.push(Problem::InvalidOffer {
+                item: item_ref.clone(),
+                supplier: offer.supplier_id,
+            });
+        }
+        valid
+    });
+    if item.preferred_offer().is_none() {
+        item.preferred_supplier = None;
+    }
+    problems
+}
+
+/// Resets the values of `item` that can't be used in a cost rollup,
+/// reporting each one.
+pub fn sanitize_item(item: &mut Item) -> Vec<Problem>
+{
+    let item_ref = ItemRef {
+        id: item.id,
+        name: Some(item.name.to_string()),
+    };
+    let mut problems = vec![];
+    if !(item.price.is_finite() && item.price >= 0.0) {
+        problems.push(Problem::InvalidPrice {
+            item: item_ref.clone(),
+            price: item.price,
+        });
+        item.price = 0.0;
+    }
+    let amount = item.amount.value();
+    if !(amount.is_finite() && amount >= 0.0) {
+        problems.push(Problem::InvalidAmount {
+            item: item_ref.clone(),
+            amount,
+        });
+        item.amount = Quantity::of(item.amount.kind(), 0.0);
+    }
+    let valid = |value: f64| value.is_finite() && value > 0.0;
+    if let Some(density) = item.density.filter(|d| !valid(d.value)) {
+        problems.push(Problem::InvalidDensity {
+            item: item_ref.clone(),
+            density: density.value,
+        });
+        item.density = None;
+    }
+    if let Some(mass) = item.unit_mass.filter(|m| !valid(m.value)) {
+        problems.push(Problem::InvalidUnitMass {
+            item: item_ref.clone(),
+            mass: mass.value,
+        });
+        item.unit_mass = None;
+    }
+    item.purchases.sort_by_key(|purchase| purchase.date);
+    item.purchases.retain(|purchase| {
+        let quantity = purchase.quantity.value();
+        let valid = purchase.price.is_finite()
+            && purchase.price >= 0.0
+            && quantity.is_finite()
+            && quantity > 0.0;
+        if !valid {
+            problems.push(Problem::InvalidPurchase {
+                item: item_ref.clone(),
+                date: purchase.date,
+            });
+        }
+        valid
+    });
+    for loss in [&mut item.trim_loss, &mut item.cooking_loss] {
+        if !(0.0..1.0).contains(loss) {
+            problems.push(Problem::InvalidLoss {
+                item: item_ref.clone(),
+                loss: *loss,
+            });
+            *loss = 0.0;
+        }
+    }
+    problems
+}
+
+#[cfg(test)]
+mod tests
+{
+    use chrono::NaiveDate;
+    use uom::si::{
+        f64::{Mass, MassDensity},
+        mass::gram,
+        mass_density::gram_per_cubic_centimeter,
+    };
+
+    use super::*;
+    use crate::model::{price::Purchase, quantity::QuantityKind};
+
+    #[test]
+    fn sanitizing_resets_bad_values()
+    {
+        let mut item = Item::new("milk");
+        item.price = f64::NAN;
+        item.amount = Quantity::of(QuantityKind::Volume, -1.0);
+        item.density =
+            Some(MassDensity::new::<gram_per_cubic_centimeter>(0.0));
+        item.unit_mass = Some(Mass::new::<gram>(f64::INFINITY));
+        item.cooking_loss = 1.5;
+        item.purchases.push(Purchase {
+            date: NaiveDate::from_ymd_opt(2024, 3, 1).unwrap(),
+            supplier: String::new(),
+            quantity: Quantity::of(QuantityKind::Volume, 0.0),
+            price: 3.0,
+        });
+
+        let problems = sanitize_item(&mut item);
+        let kinds: Vec<&str> = problems
+            .iter()
+            .map(|problem| match problem {
+                Problem::InvalidPrice { .. } => "price",
+                Problem::InvalidAmount { .. } => "amount",
+                Problem::InvalidDensity { .. } => "density",
+                Problem::InvalidUnitMass { .. } => "unit mass",
+                Problem::InvalidPurchase { .. } => "purchase",
+                Problem::InvalidLoss { .. } => "loss",
+                _ => "other",
+            })
+            .collect();
+        assert_eq!(
+            kinds,
+            [
+                "price",
+                "amount",
+                "density",
+                "unit mass",
+                "purchase",
+                "loss"
+            ]
+        );
+        assert!(problems.iter().all(Problem::is_fatal));
+        assert_eq!(item.price, 0.0);
+        assert_eq!(item.amount, Quantity::of(QuantityKind::Volume, 0.0));
+        assert!(item.density.is_none() && item.unit_mass.is_none());
+        assert!(item.purchases.is_empty());
+        assert_eq!(item.cooking_loss, 0.0);
+        assert!(sanitize_item(&mut item).is_empty());
+    }
+
+    #[test]
+    fn the_report_lists_every_problem()
+    {
+        let item = ItemRef {
+            id: Uuid::nil(),
+            name: Some("flour".into()),
+        };
+        let missing = ItemRef {
+            id: Uuid::nil(),
+            name: None,
+        };
+        let mut report = ValidationReport::default();
+        assert!(report.is_empty() && !report.is_fatal());
+        report.push(Problem::DuplicatePortion {
+            recipe: item.clone(),
+            ingredient: missing.clone(),
+            merged: true,
+        });
+        assert!(!report.is_empty() && !report.is_fatal());
+        report.push(Problem::InvalidLoss { item, loss: 2.0 });
+        assert!(report.is_fatal());
+        let nil = Uuid::nil();
+        assert_eq!(
+            report.to_string(),
+            format!(
+                "- unknown item ({nil}) appears more than once in flour \
+                 ({nil}) (merged)\n- flour ({nil}) has an invalid loss: \
+                 2\n"
+            )
+        );
+    }
+}
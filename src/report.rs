@@ -0,0 +1,300 @@
+use std::fmt::Write;
+
+use anyhow::Result;
+use uuid::Uuid;
+
+use crate::{
+    amount_format_logic1,
+    model::{
+        inventory::Inventory,
+        quantity::{Measure, Quantity},
+    },
+};
+
+/// An ingredient of a cost sheet, as the recipe uses it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SheetLine
+{
+    pub name: String,
+    pub amount: Measure,
+    /// Cost of that amount, its own components included.
+    pub cost: f64,
+    /// Share of the recipe's total cost.
+    pub percent: f64,
+}
+
+/// What a recipe costs, worked out from the inventory's rollup and
+/// ready to print.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CostSheet
+{
+    pub name: String,
+    pub lines: Vec<SheetLine>,
+    /// Price of the recipe item itself, on top of its ingredients.
+    pub own_cost: f64,
+    pub total: f64,
+    /// What the recipe yields, after cooking losses.
+    pub yield_amount: Quantity,
+    /// Cost per kg, l or unit of the yield.
+    pub unit_price: Option<(f64, &'static str)>,
+    /// Number of servings the yield is split into, if given.
+    pub servings: Option<u32>,
+}
+
+/// Print-friendly style of the HTML sheet; every column but the first
+/// holds a number.
+const STYLE: &str = "
+@page { size: A4; margin: 2cm; }
+body { font-family: sans-serif; color: #222; }
+table { border-collapse: collapse; width: 100%; }
+th, td { border-bottom: 1px solid #ccc; padding: 4px 8px; }
+th, td { text-align: right; }
+th:first-child, td:first-child { text-align: left; }
+dl { display: grid; grid-template-columns: max-content auto; }
+dt { font-weight: bold; }
+dd { margin: 0; }
+";
+
+const HEADERS: [&str; 4] = ["Ingrediente", "Quantidade", "Custo", "%"];
+
+fn money(value: f64) -> String { format!("R$ {:.2}", value) }
+
+fn escape_html(text: &str) -> String
+{
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+impl CostSheet
+{
+    /// The cost sheet of the recipe `id`, split into `servings` if
+    /// given.
+    pub fn new(
+        inventory: &Inventory,
+        id: Uuid,
+        servings: Option<u32>,
+    ) -> Result<Self>
+    {
+        let item = inventory.get_item(&id)?;
+        let breakdown = inventory.get_breakdown(id)?;
+        let mut lines = vec![];
+        for portion in inventory.get_portions(id)? {
+            let component = inventory.get_item(&portion.component_id)?;
+            let (cost, percent) = breakdown
+                .children
+                .iter()
+                .find(|child| child.item_id == component.id)
+                .map_or((0.0, 0.0), |child| (child.cost, child.percent));
+            lines.push(SheetLine {
+                name: component.name,
+                amount: portion.amount,
+                cost,
+                percent,
+            });
+        }
+        let node = inventory.get_node(&id)?;
+        let cost = inventory.get_cost(*node)?;
+        Ok(Self {
+            name: item.name,
+            lines,
+            own_cost: breakdown.own_cost,
+            total: cost.price,
+            yield_amount: cost.amount,
+            unit_price: cost.display_unit_price(),
+            servings,
+        })
+    }
+
+    /// Cost of one serving, when the number of servings is known.
+    pub fn serving_cost(&self) -> Option<f64>
+    {
+        self.servings
+            .filter(|&servings| servings > 0)
+            .map(|servings| self.total / servings as f64)
+    }
+
+    /// Label and value of the totals printed under the ingredients.
+    fn summary(&self) -> Vec<(String, String)>
+    {
+        let mut rows = vec![];
+        if self.own_cost != 0.0 {
+            rows.push(("Custo próprio".into(), money(self.own_cost)));
+        }
+        rows.push(("Custo total".into(), money(self.total)));
+        rows.push((
+            "Rendimento".into(),
+            amount_format_logic1(self.yield_amount),
+        ));
+        if let Some((price, unit)) = self.unit_price {
+            rows.push((format!("Custo por {}", unit), money(price)));
+        }
+        if let (Some(servings), Some(cost)) =
+            (self.servings, self.serving_cost())
+        {
+            rows.push((
+                format!("Custo por porção ({} porções)", servings),
+                money(cost),
+            ));
+        }
+        rows
+    }
+
+    /// Cells of the ingredient table, one row per line.
+    fn table(&self) -> Vec<[String; 4]>
+    {
+        self.lines
+            .iter()
+            .map(|line| {
+                [
+                    line.name.clone(),
+                    line.amount.to_string(),
+                    money(line.cost),
+                    format!("{:.1}%", line.percent),
+                ]
+            })
+            .collect()
+    }
+
+    pub fn to_markdown(&self) -> String
+    {
+        let row = |cells: &[String]| {
+            let cells: Vec<String> = cells
+                .iter()
+                .map(|cell| cell.replace('|', "\\|"))
+                .collect();
+            format!("| {} |", cells.join(" | "))
+        };
+        let mut out = String::new();
+        writeln!(out, "# {}\n", self.name).unwrap();
+        writeln!(out, "{}", row(&HEADERS.map(String::from))).unwrap();
+        writeln!(out, "|---|---:|---:|---:|").unwrap();
+        for cells in self.table() {
+            writeln!(out, "{}", row(&cells)).unwrap();
+        }
+        writeln!(out).unwrap();
+        for (label, value) in self.summary() {
+            writeln!(out, "- **{}:** {}", label, value).unwrap();
+        }
+        out
+    }
+
+    /// A standalone page, styled to print on A4.
+    pub fn to_html(&self) -> String
+    {
+        let row = |tag: &str, cells: &[String]| {
+            let cells: Vec<String> = cells
+                .iter()
+                .map(|cell| {
+                    format!("<{0}>{1}</{0}>", tag, escape_html(cell))
+                })
+                .collect();
+            format!("<tr>{}</tr>", cells.concat())
+        };
+        let name = escape_html(&self.name);
+        let mut out = String::new();
+        writeln!(out, "<!DOCTYPE html>\n<html lang=\"pt-BR\">").unwrap();
+        writeln!(out, "<head>\n<meta charset=\"utf-8\">").unwrap();
+        writeln!(out, "<title>{}</title>", name).unwrap();
+        writeln!(out, "<style>{}</style>\n</head>", STYLE).unwrap();
+        writeln!(out, "<body>\n<h1>{}</h1>\n<table>", name).unwrap();
+        let headers = row("th", &HEADERS.map(String::from));
+        writeln!(out, "<thead>{}</thead>\n<tbody>", headers).unwrap();
+        for cells in self.table() {
+            writeln!(out, "{}", row("td", &cells)).unwrap();
+        }
+        writeln!(out, "</tbody>\n</table>\n<dl>").unwrap();
+        for (label, value) in self.summary() {
+            writeln!(
+                out,
+                "<dt>{}</dt><dd>{}</dd>",
+                escape_html(&label),
+                escape_html(&value)
+            )
+            .unwrap();
+        }
+        writeln!(out, "</dl>\n</body>\n</html>").unwrap();
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::model::{
+        item::Item,
+        quantity::{QuantityKind, Unit},
+    };
+
+    /// A cake of 500 g of flour at 5/kg and 200 g of "sugar & spice" at
+    /// 10/kg, with the ids of the inventory and the cake.
+    fn cake() -> (Inventory, Uuid)
+    {
+        let mut inventory = Inventory::new();
+        let ingredient = |name, price| {
+            Item::builder()
+                .with_name(name)
+                .with_amount(Quantity::of(QuantityKind::Mass, 1.0))
+                .with_price(price)
+                .build()
+                .unwrap()
+        };
+        let flour = ingredient("flour", 5.0);
+        let sugar = ingredient("sugar & spice", 10.0);
+        let cake = Item::new("<cake>");
+        let ids = [flour.id, sugar.id, cake.id];
+        for item in [flour, sugar, cake] {
+            inventory.add_item(item).unwrap();
+        }
+        let grams = |value| Measure::new(value, Unit::Gram);
+        inventory
+            .create_portion(ids[0], ids[2], grams(500.0))
+            .unwrap();
+        inventory
+            .create_portion(ids[1], ids[2], grams(200.0))
+            .unwrap();
+        (inventory, ids[2])
+    }
+
+    #[test]
+    fn the_sheet_follows_the_rollup()
+    {
+        let (inventory, id) = cake();
+        let sheet = CostSheet::new(&inventory, id, Some(9)).unwrap();
+        assert_eq!(sheet.lines.len(), 2);
+        let flour =
+            sheet.lines.iter().find(|l| l.name == "flour").unwrap();
+        assert_eq!(flour.amount, Measure::new(500.0, Unit::Gram));
+        assert!((flour.cost - 2.5).abs() < 1e-9);
+        assert!((flour.percent - 2.5 / 4.5 * 100.0).abs() < 1e-9);
+        assert!((sheet.total - 4.5).abs() < 1e-9);
+        assert!((sheet.yield_amount.value() - 0.7).abs() < 1e-9);
+        assert_eq!(sheet.own_cost, 0.0);
+        assert!((sheet.serving_cost().unwrap() - 0.5).abs() < 1e-9);
+
+        let sheet = CostSheet::new(&inventory, id, Some(0)).unwrap();
+        assert_eq!(sheet.serving_cost(), None);
+    }
+
+    #[test]
+    fn sheets_render_as_markdown_and_html()
+    {
+        let (inventory, id) = cake();
+        let sheet = CostSheet::new(&inventory, id, Some(9)).unwrap();
+        let markdown = sheet.to_markdown();
+        assert!(markdown.starts_with("# <cake>\n"));
+        assert!(markdown.contains("| flour | 500 g | R$ 2.50 | 55.6% |"));
+        assert!(markdown.contains("- **Custo total:** R$ 4.50"));
+        assert!(markdown.contains("- **Rendimento:** 700 g"));
+        assert!(markdown.contains("- **Custo por kg:** R$ 6.43"));
+        assert!(markdown
+            .contains("- **Custo por porção (9 porções):** R$ 0.50"));
+
+        let html = sheet.to_html();
+        assert!(html.contains("<title>&lt;cake&gt;</title>"));
+        assert!(html.contains("sugar &amp; spice"));
+        assert!(!html.contains("<cake>"));
+    }
+}
@@ -0,0 +1,340 @@
+use std::{fmt, io};
+
+use anyhow::Result;
+use chrono::{Local, NaiveDate};
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::{
+    amount_format_exact,
+    model::{
+        error::InventoryError, inventory::Inventory, item::Item,
+        price::Purchase,
+    },
+    validation::{amount_validation, name_validation, price_validation},
+};
+
+/// A line of an imported price list. Every column but `id`,
+/// `supplier` and `date` is required; `quantity` is accepted in place
+/// of `amount`.
+#[derive(Deserialize)]
+struct Row
+{
+    #[serde(default)]
+    id: String,
+    name: String,
+    #[serde(alias = "quantity")]
+    amount: String,
+    price: String,
+    #[serde(default)]
+    supplier: String,
+    /// When the price was paid, as YYYY-MM-DD; today if empty.
+    #[serde(default)]
+    date: String,
+}
+
+/// A line of the file that was skipped, and why.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RowError
+{
+    /// Line in the file, counting the header.
+    pub line: u64,
+    pub message: String,
+}
+
+/// What an import did to the inventory.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ImportReport
+{
+    pub added: usize,
+    pub updated: usize,
+    pub errors: Vec<RowError>,
+}
+
+impl fmt::Display for ImportReport
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        writeln!(
+            f,
+            "{} added, {} updated, {} skipped",
+            self.added,
+            self.updated,
+            self.errors.len()
+        )?;
+        for error in self.errors.iter() {
+            writeln!(f, "- line {}: {}", error.line, error.message)?;
+        }
+        Ok(())
+    }
+}
+
+/// The item a row refers to: the one with its id, or else the only one
+/// with its name. `None` means the row is a new item.
+fn find_row_item(inventory: &Inventory, row: &Row)
+    -> Result<Option<Item>>
+{
+    if !row.id.is_empty() {
+        let id = Uuid::parse_str(&row.id)?;
+        return match inventory.get_item(&id) {
+            Ok(item) => Ok(Some(item)),
+            Err(InventoryError::NotFound(_)) => Ok(None),
+            Err(e) => Err(e.into()),
+        };
+    }
+    match inventory.find_item(&row.name) {
+        Ok(item) => Ok(Some(item)),
+        Err(InventoryError::NameNotFound(_)) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Adds or updates the item described by `row`, returning whether it
+/// was new.
+fn import_row(inventory: &mut Inventory, row: Row) -> Result<bool>
+{
+    let name = name_validation(row.name.clone())?;
+    let amount = amount_validation(row.amount.clone())?;
+    // recipes aren't bought, so they are exported with a price of 0
+    let price = if amount.value() == 0.0 && row.price.parse() == Ok(0.0) {
+        0.0
+    } else {
+        price_validation(row.price.clone())?
+    };
+    let date = match row.date.as_str() {
+        "" => Local::now().date_naive(),
+        date => date.parse::<NaiveDate>()?,
+    };
+    let purchase = Purchase {
+        date,
+        supplier: row.supplier.clone(),
+        quantity: amount,
+        price,
+    };
+    match find_row_item(inventory, &row)? {
+        Some(item) => {
+            // an unchanged row is only a new purchase if it is dated
+            let repeated = price == item.price
+                && amount == item.amount
+                && row.date.is_empty();
+            let purchase =
+                (amount.value() > 0.0 && !repeated).then_some(purchase);
+            let id = item.id;
+            let item = Item {
+                name,
+                amount,
+                price,
+                ..item
+            };
+            inventory.replace_item(id, item, purchase)?;
+            Ok(false)
+        }
+        None => {
+            let mut builder = Item::builder();
+            if !row.id.is_empty() {
+                builder.with_id(Uuid::parse_str(&row.id)?);
+            }
+            let mut item = builder
+                .with_name(&name)
+                .with_amount(amount)
+                .with_price(price)
+                .build()?;
+            if item.is_bought() {
+                item.add_purchase(purchase)?;
+            }
+            inventory.add_item(item)?;
+            Ok(true)
+        }
+    }
+}
+
+/// Reads a CSV price list with `id`, `name`, `amount`, `price`,
+/// `supplier` and `date` columns into `inventory`. Rows matching an
+/// item by id or name update it, the others are added, and their
+/// prices are recorded as purchases; rows that fail validation are
+/// skipped and reported.
+pub fn import(
+    inventory: &mut Inventory,
+    reader: impl io::Read,
+) -> Result<ImportReport>
+{
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(reader);
+    let headers = reader.headers()?.clone();
+    let mut report = ImportReport::default();
+    for record in reader.records() {
+        let line = match &record {
+            Ok(record) => record.position(),
+            Err(e) => e.position(),
+        }
+        .map_or(0, |p| p.line());
+        let result = record
+            .and_then(|record| record.deserialize::<Row>(Some(&headers)))
+            .map_err(Into::into)
+            .and_then(|row| import_row(inventory, row));
+        match result {
+            Ok(true) => report.added += 1,
+            Ok(false) => report.updated += 1,
+            Err(e) => report.errors.push(RowError {
+                line,
+                message: format!("{:#}", e),
+            }),
+        }
+    }
+    Ok(report)
+}
+
+/// Writes every item as CSV, in the columns `import` reads followed by
+/// the rolled-up price per kg, l or unit. Items whose cost can't be
+/// worked out leave those blank.
+pub fn export(inventory: &Inventory, writer: impl io::Write)
+    -> Result<()>
+{
+    let mut writer = csv::Writer::from_writer(writer);
+    writer.write_record([
+        "id",
+        "name",
+        "amount",
+        "price",
+        "unit_price",
+        "unit",
+    ])?;
+    for item in inventory.list_item() {
+        let unit_price = inventory
+            .get_node(&item.id)
+            .ok()
+            .and_then(|node| inventory.get_cost(*node).ok())
+            .and_then(|cost| cost.display_unit_price());
+        let (unit_price, unit) = match unit_price {
+            Some((price, unit)) => (format!("{:.2}", price), unit),
+            None => (String::new(), ""),
+        };
+        writer.write_record([
+            item.id.to_string(),
+            item.name.clone(),
+            amount_format_exact(item.amount),
+            item.price.to_string(),
+            unit_price,
+            unit.to_string(),
+        ])?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::model::{
+        price::PricePolicy,
+        quantity::{Measure, Quantity, QuantityKind, Unit},
+    };
+
+    fn flour() -> Item
+    {
+        let mut item = Item::builder()
+            .with_name("flour")
+            .with_amount(Quantity::of(QuantityKind::Mass, 1.0))
+            .with_price(5.0)
+            .build()
+            .unwrap();
+        let date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        item.add_purchase(Purchase::current(&item, date, "mill"))
+            .unwrap();
+        item
+    }
+
+    fn exported(inventory: &Inventory) -> Vec<u8>
+    {
+        let mut csv = vec![];
+        export(inventory, &mut csv).unwrap();
+        csv
+    }
+
+    #[test]
+    fn exports_import_back()
+    {
+        let mut inventory = Inventory::new();
+        let flour = flour();
+        let bread = Item::new("bread");
+        let (flour_id, bread_id) = (flour.id, bread.id);
+        inventory.add_item(flour).unwrap();
+        inventory.add_item(bread).unwrap();
+        inventory
+            .create_portion(
+                flour_id,
+                bread_id,
+                Measure::new(300.0, Unit::Gram),
+            )
+            .unwrap();
+        let csv = exported(&inventory);
+
+        let mut copy = Inventory::new();
+        let report = import(&mut copy, csv.as_slice()).unwrap();
+        assert_eq!(report.added, 2, "{}", report);
+        assert!(report.errors.is_empty());
+        for item in inventory.list_item() {
+            let imported = copy.get_item(&item.id).unwrap();
+            assert_eq!(imported.name, item.name);
+            assert_eq!(imported.amount, item.amount);
+            assert_eq!(imported.price, item.price);
+        }
+
+        // importing the same list again changes nothing
+        let before = inventory.get_item(&flour_id).unwrap();
+        let report = import(&mut inventory, csv.as_slice()).unwrap();
+        assert_eq!(report.updated, 2, "{}", report);
+        let after = inventory.get_item(&flour_id).unwrap();
+        assert_eq!(after.purchases, before.purchases);
+    }
+
+    #[test]
+    fn malformed_rows_are_reported()
+    {
+        let csv = "\
+name,amount,price,date
+sugar,1 kg,8,
+salt,a lot,2,
+pepper,100 g,NaN,
+oil,1 l,12,yesterday
+,1 kg,3,
+butter,200 g,9,2024-02-01
+";
+        let mut inventory = Inventory::new();
+        let report = import(&mut inventory, csv.as_bytes()).unwrap();
+        assert_eq!(report.added, 2);
+        let lines: Vec<u64> =
+            report.errors.iter().map(|error| error.line).collect();
+        assert_eq!(lines, [3, 4, 5, 6]);
+        assert!(inventory.find_item("salt").is_err());
+        let butter = inventory.find_item("butter").unwrap();
+        assert_eq!(butter.purchases.len(), 1);
+        assert_eq!(butter.purchases[0].date.to_string(), "2024-02-01");
+    }
+
+    #[test]
+    fn failed_rows_leave_the_item_alone()
+    {
+        let mut inventory = Inventory::new();
+        let flour = flour();
+        let id = flour.id;
+        inventory.add_item(flour).unwrap();
+        inventory
+            .set_price_policy(id, PricePolicy::Average { purchases: 0 })
+            .unwrap();
+        let before = inventory.get_item(&id).unwrap();
+        let changes = inventory.changes().len();
+
+        // the purchases in kg can't be averaged into liters
+        let csv = format!("id,name,amount,price\n{},flour,1 l,6\n", id);
+        let report = import(&mut inventory, csv.as_bytes()).unwrap();
+        assert_eq!(report.errors.len(), 1, "{}", report);
+        let after = inventory.get_item(&id).unwrap();
+        assert_eq!(after.amount, before.amount);
+        assert_eq!(after.price, before.price);
+        assert_eq!(after.purchases, before.purchases);
+        assert_eq!(inventory.changes().len(), changes);
+    }
+}
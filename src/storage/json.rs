@@ -0,0 +1,38 @@
+use std::path::{Path, PathBuf};
+
+use super::{Result, Storage};
+use crate::database::AppData;
+
+/// The whole inventory as a single JSON document, rewritten on every
+/// save.
+pub struct JsonStorage
+{
+    path: PathBuf,
+}
+
+impl JsonStorage
+{
+    pub fn new(path: &Path) -> Self
+    {
+        Self {
+            path: path.to_path_buf(),
+        }
+    }
+
+    fn filename(&self) -> String { self.path.to_string_lossy().into() }
+}
+
+impl Storage for JsonStorage
+{
+    fn load(&mut self) -> Result<AppData>
+    {
+        Ok(AppData::load(&self.filename())?)
+    }
+
+    fn save(&mut self, data: &AppData) -> Result<()>
+    {
+        Ok(data.save(&self.filename())?)
+    }
+
+    fn path(&self) -> &Path { &self.path }
+}
@@ -0,0 +1,79 @@
+use std::{io, path::Path};
+
+use thiserror::Error;
+
+use crate::{
+    database::AppData,
+    model::{change::Change, error::InventoryError, inventory::Inventory},
+};
+
+pub mod json;
+pub mod sqlite;
+
+pub use self::{json::JsonStorage, sqlite::SqliteStorage};
+
+pub type Result<T, E = StorageError> = std::result::Result<T, E>;
+
+/// Why a storage could not be read or written.
+#[derive(Debug, Error)]
+pub enum StorageError
+{
+    /// What was read is not a valid inventory.
+    #[error(transparent)]
+    Data(#[from] InventoryError),
+    #[error("failed to write data file")]
+    Write(#[from] io::Error),
+    #[error("failed to encode data")]
+    Encode(#[from] serde_json::Error),
+    #[error("failed to access database")]
+    Database(#[from] rusqlite::Error),
+    #[error("{table} row {id} is not valid JSON")]
+    Row
+    {
+        table: &'static str,
+        id: String,
+        source: serde_json::Error,
+    },
+}
+
+/// Where an inventory is persisted.
+pub trait Storage
+{
+    /// Reads everything stored, upgrading old formats.
+    fn load(&mut self) -> Result<AppData>;
+
+    /// Replaces everything stored with `data`.
+    fn save(&mut self, data: &AppData) -> Result<()>;
+
+    /// Writes the `changes` recorded by `inventory` since the last
+    /// commit. Backends without incremental writes save it whole.
+    fn commit(
+        &mut self,
+        inventory: &Inventory,
+        changes: &[Change],
+    ) -> Result<()>
+    {
+        let _ = changes;
+        self.save(&inventory.clone().into())
+    }
+
+    /// File the data is kept in.
+    fn path(&self) -> &Path;
+
+    /// Human readable location, for messages.
+    fn location(&self) -> String { self.path().to_string_lossy().into() }
+}
+
+/// Opens the backend matching the extension of `path`: SQLite for
+/// `.db`, `.sqlite` and `.sqlite3`, JSON for anything else.
+pub fn open(path: &Path) -> Result<Box<dyn Storage>>
+{
+    let extension = path.extension().and_then(|e| e.to_str());
+    let storage: Box<dyn Storage> = match extension {
+        Some("db" | "sqlite" | "sqlite3") => {
+            Box::new(SqliteStorage::open(path)?)
+        }
+        _ => Box::new(JsonStorage::new(path)),
+    };
+    Ok(storage)
+}
@@ -0,0 +1,391 @@
+use std::path::{Path, PathBuf};
+
+use chrono::Local;
+use rusqlite::{params, Connection, Transaction};
+use serde_json::{json, Value};
+
+use super::{Result, Storage, StorageError};
+use crate::{
+    database::{AppData, VERSION},
+    model::{
+        change::Change, inventory::Inventory, item::Item,
+        portion::Portion, supplier::Supplier,
+    },
+};
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS items (
+        id TEXT PRIMARY KEY,
+        name TEXT NOT NULL,
+        data TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS portions (
+        source_id TEXT NOT NULL,
+        component_id TEXT NOT NULL,
+        amount TEXT NOT NULL,
+        PRIMARY KEY (source_id, component_id)
+    );
+    CREATE TABLE IF NOT EXISTS suppliers (
+        id TEXT PRIMARY KEY,
+        data TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS prices (
+        item_id TEXT NOT NULL,
+        recorded_at TEXT NOT NULL,
+        price REAL NOT NULL
+    );
+";
+
+/// An embedded SQLite database. Items are kept as JSON documents so
+/// they follow the same migrations as the JSON backend; every price
+/// an item had is logged in `prices`.
+pub struct SqliteStorage
+{
+    path: PathBuf,
+    connection: Connection,
+}
+
+impl SqliteStorage
+{
+    pub fn open(path: &Path) -> Result<Self>
+    {
+        let connection = Connection::open(path)?;
+        connection.execute_batch(SCHEMA)?;
+        let empty: bool = connection.query_row(
+            "SELECT NOT EXISTS (SELECT 1 FROM items)",
+            [],
+            |row| row.get(0),
+        )?;
+        if empty {
+            connection.pragma_update(None, "user_version", VERSION)?;
+        }
+        Ok(Self {
+            path: path.to_path_buf(),
+            connection,
+        })
+    }
+
+    fn version(&self) -> Result<u64>
+    {
+        let version = self.connection.pragma_query_value(
+            None,
+            "user_version",
+            |row| row.get(0),
+        )?;
+        Ok(version)
+    }
+
+    /// The `data` column of every row in `table`, parsed, in the order
+    /// the rows were first written.
+    fn read_documents(&self, table: &'static str) -> Result<Vec<Value>>
+    {
+        let mut documents = vec![];
+        let mut statement = self.connection.prepare(&format!(
+            "SELECT id, data FROM {} ORDER BY rowid",
+            table
+        ))?;
+        let rows = statement.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })?;
+        for row in rows {
+            let (id, data) = row?;
+            let document = serde_json::from_str(&data).map_err(|e| {
+                StorageError::Row {
+                    table,
+                    id,
+                    source: e,
+                }
+            })?;
+            documents.push(document);
+        }
+        Ok(documents)
+    }
+
+    fn read_document(&self) -> Result<Value>
+    {
+        let items = self.read_documents("items")?;
+        let mut portions = vec![];
+        let mut statement = self.connection.prepare(
+            "SELECT source_id, component_id, amount FROM portions
+             ORDER BY rowid",
+        )?;
+        let rows = statement.query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+            ))
+        })?;
+        for row in rows {
+            let (source_id, component_id, amount) = row?;
+            let amount: Value =
+                serde_json::from_str(&amount).map_err(|e| {
+                    StorageError::Row {
+                        table: "portions",
+                        id: format!("{}/{}", source_id, component_id),
+                        source: e,
+                    }
+                })?;
+            portions.push(json!({
+                "source_id": source_id,
+                "component_id": component_id,
+                "amount": amount,
+            }));
+        }
+        let suppliers = self.read_documents("suppliers")?;
+        Ok(json!({
+            "version": self.version()?,
+            "items": items,
+            "portions": portions,
+            "suppliers": suppliers,
+        }))
+    }
+
+    /// Replaces every item, portion and supplier with those in `data`.
+    fn write_all(&mut self, data: &AppData) -> Result<()>
+    {
+        let transaction = self.connection.transaction()?;
+        transaction.execute("DELETE FROM items", [])?;
+        transaction.execute("DELETE FROM portions", [])?;
+        transaction.execute("DELETE FROM suppliers", [])?;
+        for item in data.items.iter() {
+            write_item(&transaction, item)?;
+        }
+        for portion in data.portions.iter() {
+            write_portion(&transaction, portion)?;
+        }
+        for supplier in data.suppliers.iter() {
+            write_supplier(&transaction, supplier)?;
+        }
+        transaction.pragma_update(None, "user_version", VERSION)?;
+        transaction.commit()?;
+        Ok(())
+    }
+}
+
+fn write_item(transaction: &Transaction, item: &Item) -> Result<()>
+{
+    transaction.execute(
+        "INSERT INTO items (id, name, data) VALUES (?1, ?2, ?3)
+         ON CONFLICT (id) DO UPDATE
+         SET name = excluded.name, data = excluded.data",
+        params![
+            item.id.to_string(),
+            item.name,
+            serde_json::to_string(item)?
+        ],
+    )?;
+    // only log prices that differ from the last one recorded
+    transaction.execute(
+        "INSERT INTO prices (item_id, recorded_at, price)
+         SELECT ?1, ?2, ?3
+         WHERE ?3 IS NOT (
+             SELECT price FROM prices WHERE item_id = ?1
+             ORDER BY rowid DESC LIMIT 1
+         )",
+        params![
+            item.id.to_string(),
+            Local::now().to_rfc3339(),
+            item.price
+        ],
+    )?;
+    Ok(())
+}
+
+fn write_portion(
+    transaction: &Transaction,
+    portion: &Portion,
+) -> Result<()>
+{
+    transaction.execute(
+        "INSERT INTO portions (source_id, component_id, amount)
+         VALUES (?1, ?2, ?3)
+         ON CONFLICT (source_id, component_id) DO UPDATE
+         SET amount = excluded.amount",
+        params![
+            portion.source_id.to_string(),
+            portion.component_id.to_string(),
+            serde_json::to_string(&portion.amount)?
+        ],
+    )?;
+    Ok(())
+}
+
+fn write_supplier(
+    transaction: &Transaction,
+    supplier: &Supplier,
+) -> Result<()>
+{
+    transaction.execute(
+        "INSERT INTO suppliers (id, data) VALUES (?1, ?2)
+         ON CONFLICT (id) DO UPDATE SET data = excluded.data",
+        params![supplier.id.to_string(), serde_json::to_string(supplier)?],
+    )?;
+    Ok(())
+}
+
+fn delete_portion(
+    transaction: &Transaction,
+    portion: &Portion,
+) -> Result<()>
+{
+    transaction.execute(
+        "DELETE FROM portions
+         WHERE source_id = ?1 AND component_id = ?2",
+        params![
+            portion.source_id.to_string(),
+            portion.component_id.to_string()
+        ],
+    )?;
+    Ok(())
+}
+
+impl Storage for SqliteStorage
+{
+    fn load(&mut self) -> Result<AppData>
+    {
+        let document = self.read_document()?;
+        let outdated = document["version"].as_u64() != Some(VERSION);
+        let data = AppData::from_document(document)?;
+        if outdated {
+            self.write_all(&data)?;
+        }
+        Ok(data)
+    }
+
+    fn save(&mut self, data: &AppData) -> Result<()>
+    {
+        self.write_all(data)?;
+        Ok(())
+    }
+
+    fn commit(
+        &mut self,
+        _inventory: &Inventory,
+        changes: &[Change],
+    ) -> Result<()>
+    {
+        let transaction = self.connection.transaction()?;
+        for change in changes.iter() {
+            match change {
+                Change::AddItem(item)
+                | Change::UpdateItem { after: item, .. } => {
+                    write_item(&transaction, item)?;
+                }
+                Change::RemoveItem(item) => {
+                    transaction.execute(
+                        "DELETE FROM items WHERE id = ?1",
+                        params![item.id.to_string()],
+                    )?;
+                }
+                Change::AddPortion(portion)
+                | Change::UpdatePortion { after: portion, .. } => {
+                    write_portion(&transaction, portion)?;
+                }
+                Change::RemovePortion(portion) => {
+                    delete_portion(&transaction, portion)?;
+                }
+                Change::AddSupplier(supplier)
+                | Change::UpdateSupplier {
+                    after: supplier, ..
+                } => {
+                    write_supplier(&transaction, supplier)?;
+                }
+                Change::RemoveSupplier(supplier) => {
+                    transaction.execute(
+                        "DELETE FROM suppliers WHERE id = ?1",
+                        params![supplier.id.to_string()],
+                    )?;
+                }
+            }
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    fn path(&self) -> &Path { &self.path }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use std::{env, fs};
+
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::model::{
+        inventory::RemovalPolicy,
+        quantity::{Measure, Quantity, QuantityKind, Unit},
+    };
+
+    /// Writes what `inventory` recorded since the last commit.
+    fn commit(storage: &mut SqliteStorage, inventory: &mut Inventory)
+    {
+        storage.commit(inventory, inventory.changes()).unwrap();
+        inventory.clear_changes();
+    }
+
+    fn reload(path: &Path) -> Inventory
+    {
+        let data = SqliteStorage::open(path).unwrap().load().unwrap();
+        Inventory::try_from(data).unwrap()
+    }
+
+    #[test]
+    fn committed_changes_reload()
+    {
+        let path =
+            env::temp_dir().join(format!("xef-{}.db", Uuid::new_v4()));
+        let mut storage = SqliteStorage::open(&path).unwrap();
+        let mut inventory = Inventory::new();
+        let ingredient = |name, price| {
+            Item::builder()
+                .with_name(name)
+                .with_amount(Quantity::of(QuantityKind::Mass, 1.0))
+                .with_price(price)
+                .build()
+                .unwrap()
+        };
+        let flour = ingredient("flour", 5.0);
+        let egg = ingredient("egg", 10.0);
+        let cake = Item::new("cake");
+        let (flour_id, egg_id, cake_id) = (flour.id, egg.id, cake.id);
+        for item in [flour, egg, cake] {
+            inventory.add_item(item).unwrap();
+        }
+        let grams = Measure::new(500.0, Unit::Gram);
+        inventory.create_portion(flour_id, cake_id, grams).unwrap();
+        inventory.create_portion(egg_id, cake_id, grams).unwrap();
+        commit(&mut storage, &mut inventory);
+
+        let reloaded = reload(&path);
+        let names = |inventory: &Inventory| {
+            let items = inventory.list_item();
+            items
+                .iter()
+                .map(|item| item.name.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names(&reloaded), names(&inventory));
+        assert_eq!(reloaded.get_price(cake_id).unwrap(), 7.5);
+
+        inventory
+            .update_item(flour_id, |mut item| item.price = 8.0)
+            .unwrap();
+        inventory
+            .remove_item(&egg_id, RemovalPolicy::Cascade)
+            .unwrap();
+        commit(&mut storage, &mut inventory);
+
+        // edited rows keep their place
+        let reloaded = reload(&path);
+        assert_eq!(names(&reloaded), names(&inventory));
+        assert!(reloaded.get_item(&egg_id).is_err());
+        assert_eq!(reloaded.get_all_portions().len(), 1);
+        assert_eq!(reloaded.get_price(cake_id).unwrap(), 4.0);
+
+        drop(storage);
+        fs::remove_file(&path).unwrap();
+    }
+}
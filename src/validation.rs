@@ -0,0 +1,122 @@
+use anyhow::{anyhow, Result};
+use uom::si::{
+    f64::{Mass, MassDensity},
+    mass_density::gram_per_cubic_centimeter,
+};
+
+use crate::{
+    model::quantity::{Measure, Quantity},
+    parse_amount, parse_measure,
+};
+
+pub fn name_validation(text: String) -> Result<String>
+{
+    if text.is_empty() {
+        Err(anyhow!("empty name"))
+    } else {
+        Ok(text)
+    }
+}
+pub fn amount_validation(text: String) -> Result<Quantity>
+{
+    let maybe_amount = parse_amount(text.clone()).filter(|amount| {
+        amount.value().is_finite() && amount.value() >= 0.0
+    });
+    maybe_amount
+        .ok_or(anyhow!("{} could not be parsed to an amount", text))
+}
+pub fn measure_validation(text: String) -> Result<Measure>
+{
+    let maybe_measure = parse_measure(text.clone()).filter(|measure| {
+        measure.value.is_finite() && measure.value >= 0.0
+    });
+    maybe_measure
+        .ok_or(anyhow!("{} could not be parsed to an amount", text))
+}
+pub fn price_validation(text: String) -> Result<f64>
+{
+    let n: f64 = text.parse()?;
+    if !n.is_finite() || n <= 0.0 {
+        Err(anyhow!("{} is a bad price", n))
+    } else {
+        Ok(n)
+    }
+}
+/// Density in g/ml; may be left empty.
+pub fn density_validation(text: String) -> Result<Option<MassDensity>>
+{
+    if text.is_empty() {
+        return Ok(None);
+    }
+    let n: f64 = text.parse()?;
+    if !n.is_finite() || n <= 0.0 {
+        Err(anyhow!("{} is a bad density", n))
+    } else {
+        Ok(Some(MassDensity::new::<gram_per_cubic_centimeter>(n)))
+    }
+}
+/// Mass of one unit, e.g. "50 g"; may be left empty.
+pub fn unit_mass_validation(text: String) -> Result<Option<Mass>>
+{
+    if text.is_empty() {
+        return Ok(None);
+    }
+    match parse_amount(text.clone()) {
+        Some(Quantity::Mass(mass))
+            if mass.value.is_finite() && mass.value > 0.0 =>
+        {
+            Ok(Some(mass))
+        }
+        _ => Err(anyhow!("{} could not be parsed to Mass", text)),
+    }
+}
+/// Loss as a percentage; may be left empty.
+pub fn loss_validation(text: String) -> Result<f64>
+{
+    if text.is_empty() {
+        return Ok(0.0);
+    }
+    let n: f64 = text.trim_end_matches('%').trim().parse()?;
+    if !(0.0..100.0).contains(&n) {
+        Err(anyhow!("{} is a bad loss", n))
+    } else {
+        Ok(n / 100.0)
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn numbers_must_be_finite()
+    {
+        for text in ["NaN", "inf", "-inf", "0", "-1", "x"] {
+            assert!(price_validation(text.into()).is_err(), "{}", text);
+            assert!(density_validation(text.into()).is_err(), "{}", text);
+        }
+        for text in ["NaN kg", "inf g", "-1 kg", "1 parsec"] {
+            assert!(amount_validation(text.into()).is_err(), "{}", text);
+            assert!(measure_validation(text.into()).is_err(), "{}", text);
+        }
+        assert!(unit_mass_validation("inf g".into()).is_err());
+        assert!(loss_validation("NaN".into()).is_err());
+    }
+
+    #[test]
+    fn valid_numbers_pass()
+    {
+        assert_eq!(price_validation("2.5".into()).unwrap(), 2.5);
+        assert!(density_validation("".into()).unwrap().is_none());
+        let density = density_validation("1.03".into()).unwrap().unwrap();
+        assert!(
+            (density.get::<gram_per_cubic_centimeter>() - 1.03).abs()
+                < 1e-12
+        );
+        let amount = amount_validation("500 g".into()).unwrap();
+        assert!((amount.value() - 0.5).abs() < 1e-12);
+        assert_eq!(amount_validation("0 kg".into()).unwrap().value(), 0.0);
+        assert_eq!(loss_validation("15%".into()).unwrap(), 0.15);
+    }
+}
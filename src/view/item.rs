@@ -1,9 +1,10 @@
+use chrono::Local;
 use floem::{
     event::EventListener,
     peniko::Color,
     reactive::{
-        create_effect, create_rw_signal, create_trigger, use_context,
-        RwSignal,
+        create_effect, create_memo, create_rw_signal, create_trigger,
+        use_context, RwSignal,
     },
     unit::UnitExt,
     views::{
@@ -13,19 +14,21 @@ use floem::{
     },
     IntoView,
 };
-use uom::si::{f64::Mass, mass::kilogram};
+use uom::si::mass_density::gram_per_cubic_centimeter;
 use uuid::Uuid;
 
-use super::field_border_validation;
+use super::{field_border_validation, price_label, WRONG_COLOR};
 use crate::{
-    clip_uuid, mass_format_logic1,
-    model::item::Item,
-    view::{
-        text_to_value,
-        validation::{
-            amount_validation, name_validation, price_validation,
-        },
+    amount_format_logic1, clip_uuid,
+    model::{
+        error::InventoryError, inventory::RemovalPolicy, item::Item,
+        price::Purchase, quantity::Quantity,
+    },
+    validation::{
+        amount_validation, density_validation, loss_validation,
+        name_validation, price_validation, unit_mass_validation,
     },
+    view::text_to_value,
     AppMode, AppState as State,
 };
 
@@ -45,7 +48,7 @@ impl From<Item> for ViewItem
         Self {
             id: item.id,
             name: item.name.to_string(),
-            amount: mass_format_logic1(item.amount.value),
+            amount: amount_format_logic1(item.amount),
             price: format!("R$ {}", item.price),
         }
     }
@@ -58,22 +61,41 @@ pub fn item_form() -> impl IntoView
     let name = create_rw_signal(None);
     let amount = create_rw_signal(None);
     let price = create_rw_signal(None);
+    let density = create_rw_signal(Some(None));
+    let unit_mass = create_rw_signal(Some(None));
+    let trim_loss = create_rw_signal(Some(0.0));
+    let cooking_loss = create_rw_signal(Some(0.0));
     //--- outer
     let name_text = create_rw_signal(String::new());
     let amount_text = create_rw_signal(String::new());
     let price_text = create_rw_signal(String::new());
+    let density_text = create_rw_signal(String::new());
+    let unit_mass_text = create_rw_signal(String::new());
+    let trim_loss_text = create_rw_signal(String::new());
+    let cooking_loss_text = create_rw_signal(String::new());
+    let replacement_text = create_rw_signal(String::new());
+    //--- feedback
+    let message = create_rw_signal(String::new());
+    let in_use = create_rw_signal(false);
     //--- triggers
     let clear = create_trigger();
-    let delete = create_trigger();
 
     create_effect(move |_| {
         clear.track();
         name_text.set("".into());
         amount_text.set("".into());
         price_text.set("".into());
+        density_text.set("".into());
+        unit_mass_text.set("".into());
+        trim_loss_text.set("".into());
+        cooking_loss_text.set("".into());
         name.set(None);
         amount.set(None);
         price.set(None);
+        density.set(Some(None));
+        unit_mass.set(Some(None));
+        trim_loss.set(Some(0.0));
+        cooking_loss.set(Some(0.0));
     });
     create_effect(move |_| {
         let state = state.get();
@@ -86,127 +108,281 @@ pub fn item_form() -> impl IntoView
                 let item_price = item.price;
 
                 name.set(Some(item_name.clone()));
-                amount.set(Some(Mass::new::<kilogram>(item_amount.value)));
+                amount.set(Some(item_amount));
                 price.set(Some(item_price));
 
                 name_text.set(item_name);
-                amount_text.set(mass_format_logic1(item_amount.value));
+                amount_text.set(amount_format_logic1(item_amount));
                 price_text.set(item_price.to_string());
+
+                density.set(Some(item.density));
+                unit_mass.set(Some(item.unit_mass));
+                density_text.set(
+                    item.density
+                        .map(|d| d.get::<gram_per_cubic_centimeter>())
+                        .map(|d| d.to_string())
+                        .unwrap_or_default(),
+                );
+                unit_mass_text.set(
+                    item.unit_mass
+                        .map(|m| amount_format_logic1(Quantity::Mass(m)))
+                        .unwrap_or_default(),
+                );
+
+                trim_loss.set(Some(item.trim_loss));
+                cooking_loss.set(Some(item.cooking_loss));
+                trim_loss_text.set((item.trim_loss * 100.0).to_string());
+                cooking_loss_text
+                    .set((item.cooking_loss * 100.0).to_string());
             }
         }
     });
+    // another item picked, forget what was said about the last one
+    let mode =
+        create_memo(move |_| state.with(|state| state.mode.clone()));
     create_effect(move |_| {
-        delete.track();
-        if let AppMode::EditMode(id) = state.get_untracked().mode {
-            state.update(|state| {
-                let mut model = state.model.borrow_mut();
-                if let Err(e) = model.remove_item(&id) {
-                    eprintln!("{:?}", e);
-                }
-                state.mode = AppMode::default();
+        let _ = mode.get();
+        message.set(String::new());
+        in_use.set(false);
+        replacement_text.set(String::new());
+    });
+    // recipes that use the item are only touched when asked to
+    let remove = move |policy: Option<RemovalPolicy>| {
+        let AppMode::EditMode(id) = state.get_untracked().mode else {
+            return;
+        };
+        let mut result = Ok(());
+        state.update(|state| {
+            result = state.mutate(|model| {
+                let policy = match policy {
+                    Some(policy) => policy,
+                    None => RemovalPolicy::Replace(
+                        model.find_item(replacement_text.get().trim())?.id,
+                    ),
+                };
+                model.remove_item(&id, policy).map(|_| ())
             });
+            if result.is_ok() {
+                state.mode = AppMode::default();
+            }
+        });
+        if let Err(e) = result {
+            eprintln!("{}", e);
+            if matches!(e, InventoryError::InUse { .. }) {
+                in_use.set(true);
+            }
+            message.set(e.to_string());
         }
-    });
+    };
 
     //--- produce view
-    v_stack((h_stack((
-        v_stack((
-            label(|| "nome"),
-            text_input(name_text)
-                .on_event_stop(
-                    EventListener::FocusLost,
-                    text_to_value(name_text, name_validation, name),
-                )
-                .style(field_border_validation(name)),
-        )),
-        v_stack((
-            label(|| "qtd. "),
-            text_input(amount_text)
-                .on_event_stop(
-                    EventListener::FocusLost,
-                    text_to_value(amount_text, amount_validation, amount),
-                )
-                .style(field_border_validation(amount)),
-        )),
-        v_stack((
-            label(|| "valor"),
-            text_input(price_text)
-                .on_event_stop(
-                    EventListener::FocusLost,
-                    text_to_value(price_text, price_validation, price),
-                )
-                .style(field_border_validation(price)),
-        )),
-        v_stack((
-            dyn_container(
-                move || state.get().mode,
-                move |mode| match mode {
-                    AppMode::EditMode(_) => button(|| "excluir")
-                        .on_click_stop(move |_| delete.notify())
-                        .into_any(),
-                    _ => label(|| "").style(|s| s.height(25.0)).into_any(),
-                },
-            ),
-            button(move || match state.get().mode {
-                AppMode::InsertMode => "registrar",
-                _ => "atualizar",
-            })
-            .on_click_stop(move |_| {
-                use AppMode::*;
-                let valid_item = || -> Option<Item> {
-                    let mut item_ = &mut Item::builder();
-                    let name = name.get()?;
-                    let amount = amount.get()?;
-                    let price = price.get()?;
-                    #[allow(clippy::single_match)]
-                    match state.get().mode {
-                        EditMode(src_id)
-                        | PortionMode(src_id, _)
-                        | EditPortionMode(src_id, _) => {
-                            item_ = item_.with_id(src_id);
+    v_stack((
+        h_stack((
+            v_stack((
+                label(|| "nome"),
+                text_input(name_text)
+                    .on_event_stop(
+                        EventListener::FocusLost,
+                        text_to_value(name_text, name_validation, name),
+                    )
+                    .style(field_border_validation(name)),
+            )),
+            v_stack((
+                label(|| "qtd. "),
+                text_input(amount_text)
+                    .on_event_stop(
+                        EventListener::FocusLost,
+                        text_to_value(
+                            amount_text,
+                            amount_validation,
+                            amount,
+                        ),
+                    )
+                    .style(field_border_validation(amount)),
+            )),
+            v_stack((
+                label(|| "valor"),
+                text_input(price_text)
+                    .on_event_stop(
+                        EventListener::FocusLost,
+                        text_to_value(price_text, price_validation, price),
+                    )
+                    .style(field_border_validation(price)),
+            )),
+            v_stack((
+                label(|| "densidade (g/ml)"),
+                text_input(density_text)
+                    .on_event_stop(
+                        EventListener::FocusLost,
+                        text_to_value(
+                            density_text,
+                            density_validation,
+                            density,
+                        ),
+                    )
+                    .style(field_border_validation(density)),
+            )),
+            v_stack((
+                label(|| "peso/un."),
+                text_input(unit_mass_text)
+                    .on_event_stop(
+                        EventListener::FocusLost,
+                        text_to_value(
+                            unit_mass_text,
+                            unit_mass_validation,
+                            unit_mass,
+                        ),
+                    )
+                    .style(field_border_validation(unit_mass)),
+            )),
+            v_stack((
+                label(|| "perda limpeza (%)"),
+                text_input(trim_loss_text)
+                    .on_event_stop(
+                        EventListener::FocusLost,
+                        text_to_value(
+                            trim_loss_text,
+                            loss_validation,
+                            trim_loss,
+                        ),
+                    )
+                    .style(field_border_validation(trim_loss)),
+            )),
+            v_stack((
+                label(|| "perda cocção (%)"),
+                text_input(cooking_loss_text)
+                    .on_event_stop(
+                        EventListener::FocusLost,
+                        text_to_value(
+                            cooking_loss_text,
+                            loss_validation,
+                            cooking_loss,
+                        ),
+                    )
+                    .style(field_border_validation(cooking_loss)),
+            )),
+            v_stack((
+                dyn_container(
+                    move || state.get().mode,
+                    move |mode| match mode {
+                        AppMode::EditMode(_) => button(|| "excluir")
+                            .on_click_stop(move |_| {
+                                remove(Some(RemovalPolicy::Refuse))
+                            })
+                            .into_any(),
+                        _ => label(|| "")
+                            .style(|s| s.height(25.0))
+                            .into_any(),
+                    },
+                ),
+                button(move || match state.get().mode {
+                    AppMode::InsertMode => "registrar",
+                    _ => "atualizar",
+                })
+                .on_click_stop(move |_| {
+                    use AppMode::*;
+                    let valid_item = || -> Option<Item> {
+                        let mut item_ = &mut Item::builder();
+                        let name = name.get()?;
+                        let amount = amount.get()?;
+                        let price = price.get()?;
+                        if let Some(density) = density.get()? {
+                            item_ = item_.with_density(density);
                         }
-                        _ => {}
-                    }
-                    item_
-                        .with_name(&name)
-                        .with_amount(amount)
-                        .with_price(price)
-                        .build()
-                        .ok()
-                };
-                if let Some(item) = valid_item() {
-                    state.update(|state| match state.mode {
-                        InsertMode => {
-                            let mut model = state.model.borrow_mut();
-                            model.add_item(item.clone()).unwrap();
+                        if let Some(unit_mass) = unit_mass.get()? {
+                            item_ = item_.with_unit_mass(unit_mass);
                         }
-                        EditMode(src_id)
-                        | PortionMode(src_id, _)
-                        | EditPortionMode(src_id, _) => {
-                            let mut model = state.model.borrow_mut();
-                            model
-                                .update_item(src_id, |mut i| {
-                                    *i = item.clone()
-                                })
-                                .unwrap();
+                        item_ = item_
+                            .with_trim_loss(trim_loss.get()?)
+                            .with_cooking_loss(cooking_loss.get()?);
+                        #[allow(clippy::single_match)]
+                        match state.get().mode {
+                            EditMode(src_id)
+                            | PortionMode(src_id, _)
+                            | EditPortionMode(src_id, _) => {
+                                item_ = item_.with_id(src_id);
+                            }
+                            _ => {}
                         }
-                    });
-                } else {
-                    eprintln!("failed to add item");
-                }
-                state.update(|state| state.mode = AppMode::default());
-                clear.notify();
-            })
-            .style(move |s| match state.get().mode {
-                AppMode::InsertMode => {
-                    s.border_color(Color::DARK_SLATE_GRAY)
-                }
-                _ => s.border_color(Color::DARK_GREEN),
-            }),
+                        item_
+                            .with_name(&name)
+                            .with_amount(amount)
+                            .with_price(price)
+                            .build()
+                            .ok()
+                    };
+                    if let Some(mut item) = valid_item() {
+                        // prices are recorded as bought today
+                        let today = Local::now().date_naive();
+                        let mut result = Ok(());
+                        state.update(|state| {
+                            result = match state.mode {
+                                InsertMode => state.mutate(|model| {
+                                    if item.is_bought() {
+                                        item.add_purchase(
+                                            Purchase::current(
+                                                &item, today, "",
+                                            ),
+                                        )?;
+                                    }
+                                    model.add_item(item)
+                                }),
+                                EditMode(src_id)
+                                | PortionMode(src_id, _)
+                                | EditPortionMode(src_id, _) => state
+                                    .mutate(|model| {
+                                        model
+                                            .edit_item(src_id, item, today)
+                                    }),
+                            }
+                        });
+                        // keep what was typed, to be fixed
+                        if let Err(e) = result {
+                            eprintln!("{}", e);
+                            message.set(e.to_string());
+                            return;
+                        }
+                        message.set(String::new());
+                    } else {
+                        eprintln!("failed to add item");
+                    }
+                    state.update(|state| state.mode = AppMode::default());
+                    clear.notify();
+                })
+                .style(move |s| match state.get().mode {
+                    AppMode::InsertMode => {
+                        s.border_color(Color::DARK_SLATE_GRAY)
+                    }
+                    _ => s.border_color(Color::DARK_GREEN),
+                }),
+            ))
+            .style(|s| s.margin_left(10.0)),
         ))
-        .style(|s| s.margin_left(10.0)),
+        .style(move |s| s.flex_row().padding(5.0).margin(5.0)),
+        h_stack((
+            label(move || message.get()).style(|s| s.color(WRONG_COLOR)),
+            dyn_container(
+                move || in_use.get(),
+                move |in_use| {
+                    if !in_use {
+                        return label(|| "").into_any();
+                    }
+                    h_stack((
+                        button(|| "excluir das receitas").on_click_stop(
+                            move |_| remove(Some(RemovalPolicy::Cascade)),
+                        ),
+                        label(|| "substituir por"),
+                        text_input(replacement_text),
+                        button(|| "substituir")
+                            .on_click_stop(move |_| remove(None)),
+                    ))
+                    .style(|s| s.items_center().column_gap(5.0))
+                    .into_any()
+                },
+            ),
+        ))
+        .style(|s| s.items_center().column_gap(10.0).padding_horiz(10.0)),
     ))
-    .style(move |s| s.flex_row().padding(5.0).margin(5.0)),))
 }
 
 pub fn item_list(maybe_id: Option<Uuid>) -> impl IntoView
@@ -216,24 +392,18 @@ pub fn item_list(maybe_id: Option<Uuid>) -> impl IntoView
 
     create_effect(move |_| {
         let s = state.get();
-        let model = s.model.clone();
-        let item_list: Vec<ViewItem> = model
-            .borrow()
-            .list_item()
-            .into_iter()
-            .filter_map(|item: Item| {
-                if let Some(selected_id) = maybe_id {
-                    let mut model = model.borrow().clone();
-                    let circular = model
-                        // .borrow_mut()
-                        .test_portion(item.id, selected_id)
-                        .unwrap_or(true);
-                    if !circular { Some(item.into()) } else { None }
-                } else {
-                    Some(item.into())
-                }
-            })
-            .collect();
+        let model = s.model.borrow();
+        let item_list: Vec<ViewItem> = match maybe_id {
+            Some(selected_id) => model
+                .eligible_components(selected_id)
+                .unwrap_or_default()
+                .into_iter()
+                .map(ViewItem::from)
+                .collect(),
+            None => {
+                model.list_item().into_iter().map(ViewItem::from).collect()
+            }
+        };
 
         list.set(item_list.into());
     });
@@ -252,15 +422,16 @@ pub fn item_list(maybe_id: Option<Uuid>) -> impl IntoView
                 label(move || {
                     let s = state.get_untracked();
                     let model = s.model.borrow();
-                    let amount = model.get_amount(item.id).unwrap_or(-1.0);
-                    mass_format_logic1(amount)
+                    model
+                        .get_amount(item.id)
+                        .map(amount_format_logic1)
+                        .unwrap_or("-".into())
                 })
                 .style(|s| s.min_width(120.0)),
                 label(move || {
                     let s = state.get_untracked();
                     let model = s.model.borrow();
-                    let price = model.get_price(item.id).unwrap_or(-1.0);
-                    format!("R$ {:.2}", price)
+                    price_label(model.get_price(item.id))
                 })
                 .style(|s| s.min_width(60.0)),
             ))
@@ -0,0 +1,222 @@
+use floem::{
+    reactive::{
+        create_effect, create_memo, create_rw_signal, use_context,
+        RwSignal,
+    },
+    unit::UnitExt,
+    views::{
+        container, h_stack, label, scroll, virtual_list, Decorators,
+        VirtualDirection, VirtualItemSize,
+    },
+    IntoView,
+};
+use uom::si::{mass::gram, mass_density::gram_per_cubic_centimeter};
+use uuid::Uuid;
+
+use crate::{
+    amount_format_logic1, clip_uuid,
+    journal::Entry,
+    model::{change::Change, inventory::Inventory, item::Item},
+    AppState as State,
+};
+
+#[derive(Clone)]
+struct ViewEntry
+{
+    /// Position in the journal.
+    index: usize,
+    at: String,
+    user: String,
+    description: String,
+}
+
+fn item_name(model: &Inventory, id: Uuid) -> String
+{
+    model
+        .get_item(&id)
+        .map(|item| item.name)
+        .unwrap_or_else(|_| clip_uuid(id, 8))
+}
+
+/// The fields that differ between `before` and `after`.
+fn item_diff(before: &Item, after: &Item) -> String
+{
+    let mut fields = vec![];
+    let mut compare = |field: &str, before: String, after: String| {
+        if before != after {
+            fields.push(format!("{}: {} → {}", field, before, after));
+        }
+    };
+    compare("nome", before.name.clone(), after.name.clone());
+    compare(
+        "quantidade",
+        amount_format_logic1(before.amount),
+        amount_format_logic1(after.amount),
+    );
+    let price = |item: &Item| format!("R$ {:.2}", item.price);
+    compare("preço", price(before), price(after));
+    let density = |item: &Item| match item.density {
+        Some(density) => {
+            format!("{} g/ml", density.get::<gram_per_cubic_centimeter>())
+        }
+        None => "-".into(),
+    };
+    compare("densidade", density(before), density(after));
+    let unit_mass = |item: &Item| match item.unit_mass {
+        Some(mass) => format!("{} g", mass.get::<gram>()),
+        None => "-".into(),
+    };
+    compare("peso por unidade", unit_mass(before), unit_mass(after));
+    let loss = |loss: f64| format!("{}%", loss * 100.0);
+    compare(
+        "perda no preparo",
+        loss(before.trim_loss),
+        loss(after.trim_loss),
+    );
+    compare(
+        "perda no cozimento",
+        loss(before.cooking_loss),
+        loss(after.cooking_loss),
+    );
+    compare(
+        "política de preço",
+        before.price_policy.to_string(),
+        after.price_policy.to_string(),
+    );
+    compare(
+        "compras",
+        before.purchases.len().to_string(),
+        after.purchases.len().to_string(),
+    );
+    compare(
+        "ofertas",
+        before.offers.len().to_string(),
+        after.offers.len().to_string(),
+    );
+    let preferred = |item: &Item| match item.preferred_supplier {
+        Some(id) => clip_uuid(id, 8),
+        None => "-".into(),
+    };
+    compare("fornecedor preferido", preferred(before), preferred(after));
+    if fields.is_empty() {
+        "sem alterações".into()
+    } else {
+        fields.join(", ")
+    }
+}
+
+/// What `change` did, from the point of view of the item `id`.
+fn describe(model: &Inventory, id: Uuid, change: &Change) -> String
+{
+    // a portion is shown from the recipe side or the ingredient side
+    let portion_target = |source_id: Uuid, component_id: Uuid| {
+        if source_id == id {
+            format!("ingrediente {}", item_name(model, component_id))
+        } else {
+            format!("em {}", item_name(model, source_id))
+        }
+    };
+    match change {
+        Change::AddItem(_) => "criado".into(),
+        Change::UpdateItem { before, after } => item_diff(before, after),
+        Change::RemoveItem(_) => "excluído".into(),
+        Change::AddPortion(portion) => format!(
+            "{}: adicionado, {}",
+            portion_target(portion.source_id, portion.component_id),
+            portion.amount
+        ),
+        Change::UpdatePortion { before, after } => format!(
+            "{}: {} → {}",
+            portion_target(after.source_id, after.component_id),
+            before.amount,
+            after.amount
+        ),
+        Change::RemovePortion(portion) => format!(
+            "{}: removido",
+            portion_target(portion.source_id, portion.component_id)
+        ),
+        Change::AddSupplier(supplier) => {
+            format!("fornecedor {}: criado", supplier.name)
+        }
+        Change::UpdateSupplier { after, .. } => {
+            format!("fornecedor {}: alterado", after.name)
+        }
+        Change::RemoveSupplier(supplier) => {
+            format!("fornecedor {}: excluído", supplier.name)
+        }
+    }
+}
+
+fn entry_to_view(
+    model: &Inventory,
+    id: Uuid,
+    index: usize,
+    entry: &Entry,
+) -> ViewEntry
+{
+    ViewEntry {
+        index,
+        at: entry.at.format("%d/%m/%Y %H:%M").to_string(),
+        user: entry.user.clone(),
+        description: describe(model, id, &entry.change),
+    }
+}
+
+/// Saved changes to the item `id` and its portions, newest first.
+pub fn change_history(id: Uuid) -> impl IntoView
+{
+    let state: RwSignal<State> = use_context().unwrap();
+    let list = create_rw_signal(im::Vector::<ViewEntry>::new());
+    let revision =
+        create_memo(move |_| state.with(|s| s.journal_revision));
+
+    create_effect(move |_| {
+        let _ = revision.get();
+        let s = state.get_untracked();
+        let Some(journal) = &s.journal else {
+            return;
+        };
+        match journal.for_item(id) {
+            Ok(entries) => {
+                let model = s.model.borrow();
+                list.set(
+                    entries
+                        .iter()
+                        .enumerate()
+                        .rev()
+                        .map(|(index, entry)| {
+                            entry_to_view(&model, id, index, entry)
+                        })
+                        .collect(),
+                );
+            }
+            Err(e) => eprintln!("{:#}", e),
+        }
+    });
+
+    container(
+        scroll(
+            virtual_list(
+                VirtualDirection::Vertical,
+                VirtualItemSize::Fixed(Box::new(|| 25.0)),
+                move || list.get(),
+                move |entry: &ViewEntry| entry.index,
+                move |entry| {
+                    h_stack((
+                        label(move || entry.at.clone())
+                            .style(|s| s.min_width(120.0)),
+                        label(move || entry.user.clone())
+                            .style(|s| s.min_width(80.0)),
+                        label(move || entry.description.clone()),
+                    ))
+                    .style(move |s| s.padding_top(5.0).padding_horiz(15.0))
+                },
+            )
+            .style(move |s| s.flex_col().width_full().padding_top(5.0)),
+        )
+        .style(|s| s.width(100.pct()).height(100.pct())),
+    )
+    .style(|s| {
+        s.height_full().padding_vert(15.0).flex_col().items_center()
+    })
+}
@@ -1,6 +1,7 @@
 use anyhow::Result;
 use floem::{
-    event::Event,
+    event::{Event, EventListener},
+    keyboard::Key,
     peniko::Color,
     reactive::{create_rw_signal, use_context, RwSignal},
     style::Style,
@@ -9,10 +10,20 @@ use floem::{
 };
 
 pub mod item;
+pub mod journal;
 pub mod portion;
-pub mod validation;
-use self::item::{item_form, item_list};
+pub mod supplier;
+pub mod trend;
+pub mod workspace;
+use self::{
+    item::{item_form, item_list},
+    journal::change_history,
+    supplier::{offer_panel, supplier_page},
+    trend::cost_trend,
+    workspace::workspace_switcher,
+};
 use crate::{
+    model::error::InventoryError,
     view::portion::{portion_form, portion_list},
     AppState as State,
 };
@@ -28,6 +39,18 @@ fn text_to_value<T: 'static>(
     }
 }
 
+/// Price label; when the rollup fails, names the broken ingredient
+/// instead.
+fn price_label(price: Result<f64, InventoryError>) -> String
+{
+    match price {
+        Ok(price) => format!("R$ {:.2}", price),
+        Err(InventoryError::Cost(e)) => format!("erro: {}", e.item().1),
+        Err(InventoryError::Conversion(e)) => format!("erro: {}", e.name),
+        Err(_) => "-".into(),
+    }
+}
+
 //--- constants
 const WRONG_COLOR: Color = Color::ORANGE_RED;
 const RIGHT_COLOR: Color = Color::FOREST_GREEN;
@@ -47,10 +70,47 @@ pub fn field_border_validation<T: Clone + 'static>(
     }
 }
 
+/// Undoes or redoes an edit, through `step`.
+fn history(
+    state: RwSignal<State>,
+    step: fn(&mut State) -> Result<bool, InventoryError>,
+)
+{
+    let mut result = Ok(false);
+    state.update(|state| result = step(state));
+    if let Err(e) = result {
+        eprintln!("{}", e);
+    }
+}
+
+/// Ctrl+Z undoes, Ctrl+Shift+Z and Ctrl+Y redo.
+fn history_shortcuts(state: RwSignal<State>) -> impl Fn(&Event)
+{
+    move |event| {
+        let Event::KeyDown(event) = event else {
+            return;
+        };
+        let modifiers = event.modifiers;
+        if !(modifiers.control() || modifiers.meta()) {
+            return;
+        }
+        let Key::Character(text) = &event.key.logical_key else {
+            return;
+        };
+        match text.to_lowercase().as_str() {
+            "z" if modifiers.shift() => history(state, State::redo),
+            "z" => history(state, State::undo),
+            "y" => history(state, State::redo),
+            _ => {}
+        }
+    }
+}
+
 #[derive(Clone, PartialEq)]
 enum Page
 {
     ItemPage,
+    SupplierPage,
 }
 
 fn main_page() -> impl IntoView
@@ -70,7 +130,14 @@ fn main_page() -> impl IntoView
         dyn_container(
             move || state.get().mode,
             |mode| match mode {
-                EditMode(src_id) => portion_list(src_id).into_any(),
+                EditMode(src_id) => v_stack((
+                    h_stack((
+                        portion_list(src_id),
+                        change_history(src_id),
+                    )),
+                    h_stack((offer_panel(src_id), cost_trend(src_id))),
+                ))
+                .into_any(),
                 PortionMode(src_id, id) | EditPortionMode(src_id, id) => {
                     h_stack((
                         portion_form(src_id, id),
@@ -90,19 +157,50 @@ pub fn app_view() -> impl IntoView
     let state: RwSignal<State> = use_context().unwrap();
 
     v_stack((
-        h_stack((button(move || format!("{:?}", state.get().mode))
-            .on_click_stop(move |_| {
-                page.set(Page::ItemPage);
+        h_stack((
+            button(move || format!("{:?}", state.get().mode))
+                .on_click_stop(move |_| {
+                    page.set(Page::ItemPage);
+                })
+                .style(|s| s.margin_bottom(20)),
+            button(|| "fornecedores")
+                .on_click_stop(move |_| page.set(Page::SupplierPage))
+                .style(|s| s.margin_left(10.0)),
+            button(|| "desfazer")
+                .on_click_stop(move |_| history(state, State::undo))
+                .disabled(move || !state.get().can_undo())
+                .style(|s| s.margin_left(10.0)),
+            button(|| "refazer")
+                .on_click_stop(move |_| history(state, State::redo))
+                .disabled(move || !state.get().can_redo())
+                .style(|s| s.margin_left(5.0)),
+            label(move || {
+                let state = state.get();
+                match state.save_error {
+                    Some(e) => format!("erro ao salvar: {}", e),
+                    None if state.dirty => "alterações não salvas".into(),
+                    None => String::new(),
+                }
             })
-            .style(|s| s.margin_bottom(20)),)),
+            .style(move |s| {
+                let color = match state.get().save_error {
+                    Some(_) => WRONG_COLOR,
+                    None => Color::GRAY,
+                };
+                s.color(color).margin_left(10.0)
+            }),
+            workspace_switcher(),
+        )),
         dyn_container(
             move || page.get(),
             move |page| match page {
                 Page::ItemPage => main_page().into_any(),
+                Page::SupplierPage => supplier_page().into_any(),
             },
         )
         .style(|s| s.padding(10).border(1).size_full()),
     ))
+    .on_event_stop(EventListener::KeyDown, history_shortcuts(state))
     .style(|s| {
         s.width_full()
             .height_full()
@@ -13,13 +13,15 @@ use floem::{
     },
     IntoView,
 };
-use uom::si::f64::Mass;
 use uuid::Uuid;
 
-use super::validation::amount_validation;
+use super::price_label;
 use crate::{
-    mass_format_logic1,
-    model::{inventory::Inventory, item::Item, portion::Portion},
+    model::{
+        conversion::convert, error::InventoryError, inventory::Inventory,
+        item::Item, portion::Portion, quantity::Measure,
+    },
+    validation::measure_validation,
     view::text_to_value,
     AppMode, AppState as State,
 };
@@ -41,9 +43,13 @@ pub fn portion_to_view(
     let component_id = portion.component_id;
     let item = inventory.get_item(&component_id)?;
     let ingredient_name = item.name.to_string();
-    let amount = mass_format_logic1(portion.amount);
-    let price = inventory.get_price(portion.source_id)? * portion.amount;
-    let price = format!("R$ {:.2}", price);
+    let amount = portion.amount.to_string();
+    let component = inventory.get_node(&component_id)?;
+    let price = || -> Result<f64, InventoryError> {
+        let unit_price = inventory.get_unit_price(*component)?;
+        Ok(unit_price * inventory.get_portion_amount(&portion)?.value())
+    };
+    let price = price_label(price());
 
     let view_portion = ViewPortion {
         component_id,
@@ -63,7 +69,7 @@ pub fn portion_form(src_id: Uuid, id: Uuid) -> impl IntoView
     let item: RwSignal<Option<Item>> = create_rw_signal(None);
     let source: RwSignal<Option<Item>> = create_rw_signal(None);
     //--- inner
-    let amount: RwSignal<Option<Mass>> = create_rw_signal(None);
+    let amount: RwSignal<Option<Measure>> = create_rw_signal(None);
     //--- outer
     let ing_name_text = create_rw_signal(String::new());
     let amount_text = create_rw_signal(String::new());
@@ -72,11 +78,17 @@ pub fn portion_form(src_id: Uuid, id: Uuid) -> impl IntoView
     create_effect(move |_| {
         let state = state.get();
         let inventory = state.model.borrow();
-        if let Some(amount) = amount.get() {
-            if let Ok(item_index) = inventory.get_node(&id.get()) {
-                let src_price = inventory.get_unit_price(*item_index);
-                let price = amount.value * src_price;
-                price_text.set(price.to_string());
+        if let (Some(amount), Some(item)) = (amount.get(), item.get()) {
+            let price = || -> Result<f64> {
+                let amount =
+                    convert(&item, amount.quantity(), item.amount.kind())?;
+                let item_index = inventory.get_node(&item.id)?;
+                let src_price = inventory.get_unit_price(*item_index)?;
+                Ok(amount.value() * src_price)
+            };
+            match price() {
+                Ok(price) => price_text.set(price.to_string()),
+                Err(e) => price_text.set(e.to_string()),
             }
         }
     });
@@ -90,6 +102,14 @@ pub fn portion_form(src_id: Uuid, id: Uuid) -> impl IntoView
         if let Ok(i) = inventory.get_item(&id.get()) {
             item.set(Some(i));
         }
+        if let AppMode::EditPortionMode(_, _) = state.mode {
+            if let Ok(portion) =
+                inventory.get_portion(id.get(), src_id.get())
+            {
+                amount.set(Some(portion.amount));
+                amount_text.set(portion.amount.to_string());
+            }
+        }
     });
     create_effect(move |_| {
         if let Some(item) = item.get() {
@@ -101,7 +121,7 @@ pub fn portion_form(src_id: Uuid, id: Uuid) -> impl IntoView
         label(move || ing_name_text.get()),
         text_input(amount_text).on_event_stop(
             EventListener::FocusLost,
-            text_to_value(amount_text, amount_validation, amount),
+            text_to_value(amount_text, measure_validation, amount),
         ),
         label(move || price_text.get()),
         dyn_container(
@@ -113,8 +133,11 @@ pub fn portion_form(src_id: Uuid, id: Uuid) -> impl IntoView
                             state.get_untracked().mode
                         {
                             state.update(|state| {
-                                let mut model = state.model.borrow_mut();
-                                model.remove_portion(id, src_id).unwrap();
+                                state
+                                    .mutate(|model| {
+                                        model.remove_portion(id, src_id)
+                                    })
+                                    .unwrap();
                                 state.mode = AppMode::EditMode(src_id);
                             });
                         }
@@ -123,17 +146,32 @@ pub fn portion_form(src_id: Uuid, id: Uuid) -> impl IntoView
                 _ => label(|| "").style(|s| s.height(25.0)).into_any(),
             },
         ),
-        button(|| "Adicionar").on_click_stop(move |_| {
+        button(move || match state.get().mode {
+            AppMode::EditPortionMode(_, _) => "atualizar",
+            _ => "Adicionar",
+        })
+        .on_click_stop(move |_| {
             let source_id = source.get().unwrap().id;
             let item_id = item.get().unwrap().id;
             if amount.get().is_none() {
                 return;
             }
-            let amount = amount.get().unwrap().value;
+            let amount = amount.get().unwrap();
 
             state.update(|state| {
-                let mut model = state.model.borrow_mut();
-                model.create_portion(item_id, source_id, amount).unwrap();
+                let mode = state.mode.clone();
+                let result = state.mutate(|model| match mode {
+                    AppMode::EditPortionMode(_, _) => model
+                        .update_portion(item_id, source_id, amount)
+                        .map(|_| ()),
+                    _ => model
+                        .create_portion(item_id, source_id, amount)
+                        .map(|_| ()),
+                });
+                if let Err(e) = result {
+                    eprintln!("{}", e);
+                    price_text.set(e.to_string());
+                }
             });
         }),
     ))
@@ -190,10 +228,7 @@ pub fn portion_list(src_id: Uuid) -> impl IntoView
                         let id = view_portion.component_id;
                         let model = s.model.borrow();
 
-                        if let Ok(_portion) =
-                            model.get_portion(id, src_id.get())
-                        {
-                            dbg!(_portion);
+                        if model.get_portion(id, src_id.get()).is_ok() {
                             state.update(|state| {
                                 state.mode = AppMode::EditPortionMode(
                                     src_id.get(),
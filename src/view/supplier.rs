@@ -0,0 +1,250 @@
+use chrono::Local;
+use floem::{
+    reactive::{create_effect, create_rw_signal, use_context, RwSignal},
+    unit::UnitExt,
+    views::{
+        button, container, h_stack, label, scroll, text_input, v_stack,
+        virtual_list, Decorators, VirtualDirection, VirtualItemSize,
+    },
+    IntoView,
+};
+use uuid::Uuid;
+
+use crate::{
+    amount_format_logic1,
+    model::{
+        error::InventoryError,
+        inventory::Inventory,
+        supplier::{Offer, OfferComparison, Supplier},
+    },
+    validation::{amount_validation, name_validation, price_validation},
+    AppState as State,
+};
+
+/// Runs `change` as an edit, showing why it failed in `message`.
+/// Returns whether it succeeded.
+fn edit(
+    state: RwSignal<State>,
+    message: RwSignal<String>,
+    change: impl FnOnce(&mut Inventory) -> Result<(), InventoryError>,
+) -> bool
+{
+    let mut result = Ok(());
+    state.update(|state| result = state.mutate(change));
+    match result {
+        Ok(()) => {
+            message.set(String::new());
+            true
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            message.set(e.to_string());
+            false
+        }
+    }
+}
+
+/// Registers suppliers and lists them, each with a button to remove
+/// it.
+pub fn supplier_page() -> impl IntoView
+{
+    let state: RwSignal<State> = use_context().unwrap();
+    let list = create_rw_signal(im::Vector::<Supplier>::new());
+    let name_text = create_rw_signal(String::new());
+    let contact_text = create_rw_signal(String::new());
+    let message = create_rw_signal(String::new());
+
+    create_effect(move |_| {
+        let s = state.get();
+        let model = s.model.borrow();
+        list.set(model.list_suppliers().into());
+    });
+
+    let register = move || {
+        let name = match name_validation(name_text.get()) {
+            Ok(name) => name,
+            Err(e) => {
+                message.set(e.to_string());
+                return;
+            }
+        };
+        let mut supplier = Supplier::new(&name);
+        supplier.contact = contact_text.get().trim().to_string();
+        if edit(state, message, |model| model.add_supplier(supplier)) {
+            name_text.set(String::new());
+            contact_text.set(String::new());
+        }
+    };
+
+    v_stack((
+        h_stack((
+            v_stack((label(|| "nome"), text_input(name_text))),
+            v_stack((label(|| "contato"), text_input(contact_text))),
+            button(|| "registrar").on_click_stop(move |_| register()),
+            label(move || message.get()),
+        ))
+        .style(|s| s.items_end().column_gap(10.0)),
+        container(scroll(
+            virtual_list(
+                VirtualDirection::Vertical,
+                VirtualItemSize::Fixed(Box::new(|| 30.0)),
+                move || list.get(),
+                move |supplier: &Supplier| supplier.clone(),
+                move |supplier| {
+                    let id = supplier.id;
+                    h_stack((
+                        label(move || supplier.name.clone())
+                            .style(|s| s.min_width(150.0)),
+                        label(move || supplier.contact.clone())
+                            .style(|s| s.min_width(150.0)),
+                        button(|| "excluir").on_click_stop(move |_| {
+                            edit(state, message, |model| {
+                                model.remove_supplier(&id).map(|_| ())
+                            });
+                        }),
+                    ))
+                    .style(|s| s.items_center().padding_horiz(15.0))
+                },
+            )
+            .style(|s| s.flex_col().width_full().padding_top(5.0)),
+        ))
+        .style(|s| s.width(100.pct()).height(100.pct())),
+    ))
+    .style(|s| s.padding(10.0).row_gap(10.0))
+}
+
+/// Price per kg, l or unit of an offer.
+fn unit_price_label(line: &OfferComparison) -> String
+{
+    match line.cost.display_unit_price() {
+        Some((price, unit)) => format!("R$ {:.2}/{}", price, unit),
+        None => "-".into(),
+    }
+}
+
+/// The offers for the item `id`, cheapest first, with a form to add
+/// one and buttons to prefer or remove each.
+pub fn offer_panel(id: Uuid) -> impl IntoView
+{
+    let state: RwSignal<State> = use_context().unwrap();
+    let list = create_rw_signal(im::Vector::<OfferComparison>::new());
+    let supplier_text = create_rw_signal(String::new());
+    let pack_text = create_rw_signal(String::new());
+    let price_text = create_rw_signal(String::new());
+    let lead_time_text = create_rw_signal(String::new());
+    let message = create_rw_signal(String::new());
+
+    create_effect(move |_| {
+        let s = state.get();
+        let model = s.model.borrow();
+        match model.compare_offers(id) {
+            Ok(offers) => list.set(offers.into()),
+            Err(e) => message.set(e.to_string()),
+        }
+    });
+
+    let valid_offer = move || -> anyhow::Result<Offer> {
+        let s = state.get_untracked();
+        let model = s.model.borrow();
+        let supplier = model.find_supplier(supplier_text.get().trim())?;
+        let lead_time = lead_time_text.get();
+        Ok(Offer {
+            supplier_id: supplier.id,
+            pack: amount_validation(pack_text.get())?,
+            price: price_validation(price_text.get())?,
+            lead_time_days: match lead_time.trim() {
+                "" => 0,
+                days => days.parse()?,
+            },
+        })
+    };
+
+    v_stack((
+        label(|| "fornecedores, do mais barato"),
+        h_stack((
+            v_stack((label(|| "fornecedor"), text_input(supplier_text))),
+            v_stack((label(|| "embalagem"), text_input(pack_text))),
+            v_stack((label(|| "valor"), text_input(price_text))),
+            v_stack((
+                label(|| "prazo (dias)"),
+                text_input(lead_time_text),
+            )),
+            button(|| "ofertar").on_click_stop(
+                move |_| match valid_offer() {
+                    Ok(offer) => {
+                        edit(state, message, |model| {
+                            model.set_offer(
+                                id,
+                                offer,
+                                Local::now().date_naive(),
+                            )
+                        });
+                    }
+                    Err(e) => message.set(format!("{:#}", e)),
+                },
+            ),
+        ))
+        .style(|s| s.items_end().column_gap(5.0)),
+        label(move || message.get()),
+        container(scroll(
+            virtual_list(
+                VirtualDirection::Vertical,
+                VirtualItemSize::Fixed(Box::new(|| 30.0)),
+                move || list.get(),
+                // every field, so that changed offers are drawn again
+                move |line: &OfferComparison| format!("{:?}", line),
+                move |line| {
+                    let supplier_id = line.supplier.id;
+                    let preferred = line.preferred;
+                    let name = line.supplier.name.clone();
+                    let pack = amount_format_logic1(line.offer.pack);
+                    let price = format!("R$ {:.2}", line.offer.price);
+                    let unit_price = unit_price_label(&line);
+                    let lead_time =
+                        format!("{} dias", line.offer.lead_time_days);
+                    h_stack((
+                        label(move || name.clone())
+                            .style(|s| s.min_width(120.0)),
+                        label(move || pack.clone())
+                            .style(|s| s.min_width(80.0)),
+                        label(move || price.clone())
+                            .style(|s| s.min_width(80.0)),
+                        label(move || unit_price.clone())
+                            .style(|s| s.min_width(100.0)),
+                        label(move || lead_time.clone())
+                            .style(|s| s.min_width(60.0)),
+                        button(move || {
+                            if preferred {
+                                "preferido"
+                            } else {
+                                "preferir"
+                            }
+                        })
+                        .on_click_stop(
+                            move |_| {
+                                // clicked again, no longer preferred
+                                let choice =
+                                    (!preferred).then_some(supplier_id);
+                                let today = Local::now().date_naive();
+                                edit(state, message, |model| {
+                                    model.set_preferred_supplier(
+                                        id, choice, today,
+                                    )
+                                });
+                            },
+                        ),
+                        button(|| "excluir").on_click_stop(move |_| {
+                            edit(state, message, |model| {
+                                model.remove_offer(id, supplier_id)
+                            });
+                        }),
+                    ))
+                    .style(|s| s.items_center().padding_horiz(15.0))
+                },
+            )
+            .style(|s| s.flex_col().width_full().padding_top(5.0)),
+        ))
+        .style(|s| s.width(100.pct()).height(100.pct())),
+    ))
+    .style(|s| s.padding_vert(15.0).row_gap(5.0))
+}
@@ -0,0 +1,147 @@
+use chrono::{Days, Local, NaiveDate};
+use floem::{
+    event::EventListener,
+    peniko::Color,
+    reactive::{
+        create_effect, create_memo, create_rw_signal, use_context,
+        RwSignal,
+    },
+    views::{
+        container, empty, h_stack, label, scroll, text_input, v_stack,
+        virtual_list, Decorators, VirtualDirection, VirtualItemSize,
+    },
+    IntoView,
+};
+use uuid::Uuid;
+
+use super::{field_border_validation, text_to_value, WRONG_COLOR};
+use crate::{
+    model::cost::CostPoint, validation::price_validation,
+    AppState as State,
+};
+
+/// Height of the tallest bar.
+const CHART_HEIGHT: f64 = 150.0;
+
+/// The day from which the price stayed above `sale_price`, if it is
+/// above it now.
+fn unprofitable_since(
+    series: &im::Vector<CostPoint>,
+    sale_price: f64,
+) -> Option<NaiveDate>
+{
+    series
+        .iter()
+        .rev()
+        .take_while(|point| point.price > sale_price)
+        .last()
+        .map(|point| point.date)
+}
+
+/// Cost of the recipe `id` over the last year, one bar per change,
+/// against the price it is sold for.
+pub fn cost_trend(id: Uuid) -> impl IntoView
+{
+    let state: RwSignal<State> = use_context().unwrap();
+    let series = create_rw_signal(im::Vector::<CostPoint>::new());
+    let sale_text = create_rw_signal(String::new());
+    let sale_price = create_rw_signal(None);
+    let revision = create_memo(move |_| state.with(|s| s.model_revision));
+
+    create_effect(move |_| {
+        let _ = revision.get();
+        let s = state.get_untracked();
+        let model = s.model.borrow();
+        let to = Local::now().date_naive();
+        match model.get_price_series(id, to - Days::new(365), to) {
+            Ok(points) => series.set(points.into()),
+            Err(e) => {
+                eprintln!("{}", e);
+                series.set(im::Vector::new());
+            }
+        }
+    });
+
+    let highest = move || {
+        series
+            .get()
+            .iter()
+            .map(|point| point.price)
+            .chain(sale_price.get())
+            .fold(0.0, f64::max)
+    };
+
+    v_stack((
+        h_stack((
+            label(|| "preço de venda"),
+            text_input(sale_text)
+                .on_event_stop(
+                    EventListener::FocusLost,
+                    text_to_value(sale_text, price_validation, sale_price),
+                )
+                .style(field_border_validation(sale_price)),
+            label(move || {
+                let Some(sale) = sale_price.get() else {
+                    return String::new();
+                };
+                match unprofitable_since(&series.get(), sale) {
+                    Some(date) => format!(
+                        "custo acima do preço desde {}",
+                        date.format("%d/%m/%Y")
+                    ),
+                    None => "custo abaixo do preço".into(),
+                }
+            })
+            .style(move |s| {
+                let above = sale_price.get().is_some_and(|sale| {
+                    unprofitable_since(&series.get(), sale).is_some()
+                });
+                s.color(if above { WRONG_COLOR } else { Color::GRAY })
+            }),
+        ))
+        .style(|s| s.items_center()),
+        container(scroll(
+            virtual_list(
+                VirtualDirection::Horizontal,
+                VirtualItemSize::Fixed(Box::new(|| 60.0)),
+                move || series.get(),
+                move |point: &CostPoint| point.date,
+                move |point| {
+                    v_stack((
+                        label(move || format!("{:.2}", point.price)),
+                        empty().style(move |s| {
+                            let highest = highest();
+                            let height = if highest > 0.0 {
+                                point.price / highest * CHART_HEIGHT
+                            } else {
+                                0.0
+                            };
+                            let above = sale_price
+                                .get()
+                                .is_some_and(|sale| point.price > sale);
+                            s.width(40.0).height(height).background(
+                                if above {
+                                    WRONG_COLOR
+                                } else {
+                                    Color::STEEL_BLUE
+                                },
+                            )
+                        }),
+                        label(move || {
+                            point.date.format("%d/%m").to_string()
+                        }),
+                    ))
+                    .style(|s| {
+                        s.height_full()
+                            .justify_end()
+                            .items_center()
+                            .padding_horiz(10.0)
+                    })
+                },
+            )
+            .style(|s| s.height(CHART_HEIGHT + 50.0)),
+        ))
+        .style(|s| s.width_full()),
+    ))
+    .style(|s| s.padding_vert(15.0).row_gap(10.0))
+}
@@ -0,0 +1,110 @@
+use floem::{
+    reactive::{create_effect, create_rw_signal, use_context, RwSignal},
+    views::{
+        button, dyn_container, h_stack, label, text_input, virtual_list,
+        Decorators, VirtualDirection, VirtualItemSize,
+    },
+    IntoView,
+};
+
+use crate::{model::error::InventoryError, AppState as State};
+
+/// Lists the workspaces in the data directory and switches between
+/// them; typing a new name creates one.
+pub fn workspace_switcher() -> impl IntoView
+{
+    let state: RwSignal<State> = use_context().unwrap();
+    let names = create_rw_signal(im::Vector::<String>::new());
+    let name_text = create_rw_signal(String::new());
+    let message = create_rw_signal(String::new());
+    // workspace refused for being damaged, until repaired or left
+    let damaged = create_rw_signal(None::<String>);
+
+    create_effect(move |_| {
+        let state = state.get();
+        if let Some(workspaces) = state.workspaces {
+            match workspaces.list() {
+                Ok(list) => names.set(list.into()),
+                Err(e) => message.set(e.to_string()),
+            }
+        }
+    });
+
+    let open = move |name: String, repair: bool| {
+        let mut result = Ok(None);
+        state.update(|state| result = state.open_workspace(&name, repair));
+        damaged.set(None);
+        match result {
+            Ok(Some(report)) => {
+                eprint!("{}", report);
+                message.set(format!(
+                    "{} problema(s) corrigido(s)",
+                    report.problems.len()
+                ));
+            }
+            Ok(None) => message.set(String::new()),
+            Err(e)
+                if matches!(
+                    e.downcast_ref::<InventoryError>(),
+                    Some(InventoryError::Invalid(_))
+                ) =>
+            {
+                eprintln!("{:#}", e);
+                message.set(format!("{} está danificado", name));
+                damaged.set(Some(name));
+            }
+            Err(e) => {
+                eprintln!("{:#}", e);
+                message.set(format!("{:#}", e));
+            }
+        }
+    };
+
+    let list = virtual_list(
+        VirtualDirection::Horizontal,
+        VirtualItemSize::Fixed(Box::new(|| 100.0)),
+        move || names.get(),
+        move |name: &String| name.clone(),
+        move |name: String| {
+            label(move || name.clone()).style(|s| s.padding_horiz(10.0))
+        },
+    );
+    let selected = list.selection();
+
+    h_stack((
+        label(move || {
+            let workspace = state.get().workspace;
+            format!("espaço: {}", workspace.unwrap_or("-".into()))
+        })
+        .style(|s| s.min_width(120.0)),
+        list.style(|s| s.flex_row().min_width(100.0)).on_select(
+            move |maybe_index| {
+                if let Some(index) = maybe_index {
+                    selected.set(None);
+                    if let Some(name) = names.get_untracked().get(index) {
+                        open(name.clone(), false);
+                    }
+                }
+            },
+        ),
+        text_input(name_text).style(|s| s.min_width(100.0)),
+        button(|| "abrir").on_click_stop(move |_| {
+            let name = name_text.get().trim().to_string();
+            if !name.is_empty() {
+                open(name, false);
+                name_text.set(String::new());
+            }
+        }),
+        label(move || message.get()),
+        dyn_container(
+            move || damaged.get(),
+            move |damaged| match damaged {
+                Some(name) => button(|| "reparar")
+                    .on_click_stop(move |_| open(name.clone(), true))
+                    .into_any(),
+                None => label(|| "").into_any(),
+            },
+        ),
+    ))
+    .style(|s| s.items_center().margin_left(10.0))
+}
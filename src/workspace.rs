@@ -0,0 +1,187 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Result};
+use clap::Args;
+
+use crate::{
+    model::{error::InventoryError, validation::ValidationReport},
+    storage, AppState,
+};
+
+pub const DEFAULT_WORKSPACE: &str = "default";
+
+/// Data file kept in the working directory before there were
+/// workspaces.
+pub const LEGACY_FILE: &str = "xef.json";
+
+/// Extensions a workspace file may have, in the order they are looked
+/// up. New workspaces use the first one.
+const EXTENSIONS: [&str; 4] = ["json", "db", "sqlite", "sqlite3"];
+
+/// A directory of named inventories, one data file each.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Workspaces
+{
+    dir: PathBuf,
+}
+
+impl Workspaces
+{
+    pub fn new(dir: impl Into<PathBuf>) -> Self
+    {
+        Self { dir: dir.into() }
+    }
+
+    /// `xef` under the user's data directory (`$XDG_DATA_HOME` on
+    /// Linux), or the working directory when there is none.
+    pub fn default_dir() -> PathBuf
+    {
+        dirs::data_dir()
+            .map(|dir| dir.join("xef"))
+            .unwrap_or_else(|| PathBuf::from("."))
+    }
+
+    pub fn dir(&self) -> &Path { &self.dir }
+
+    /// Names of the workspaces in the directory, sorted.
+    pub fn list(&self) -> io::Result<Vec<String>>
+    {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                return Ok(vec![])
+            }
+            Err(e) => return Err(e),
+        };
+        let mut names = vec![];
+        for entry in entries {
+            let path = entry?.path();
+            let extension = path.extension().and_then(|e| e.to_str());
+            if !extension.is_some_and(|e| EXTENSIONS.contains(&e)) {
+                continue;
+            }
+            if let Some(stem) = path.file_stem().and_then(|s| s.to_str()) {
+                names.push(stem.to_string());
+            }
+        }
+        names.sort();
+        names.dedup();
+        Ok(names)
+    }
+
+    /// Data file of the workspace `name`, which may not exist yet.
+    /// Existing files keep their backend.
+    pub fn path(&self, name: &str) -> Result<PathBuf>
+    {
+        let invalid = name.is_empty()
+            || name.starts_with('.')
+            || name.contains(['/', '\\']);
+        if invalid {
+            bail!("invalid workspace name: {:?}", name);
+        }
+        let path = EXTENSIONS
+            .iter()
+            .map(|extension| {
+                self.dir.join(format!("{}.{}", name, extension))
+            })
+            .find(|path| path.exists())
+            .unwrap_or_else(|| {
+                self.dir.join(format!("{}.{}", name, EXTENSIONS[0]))
+            });
+        Ok(path)
+    }
+}
+
+/// Command line options choosing the data to open, shared by the
+/// binaries.
+#[derive(Args, Clone, Debug)]
+pub struct Location
+{
+    /// Data file to open instead of a workspace, e.g. an old xef.json.
+    #[arg(long, env = "XEF_FILE")]
+    pub file: Option<PathBuf>,
+    /// Directory holding the workspaces [default: xef in the user data
+    /// directory]
+    #[arg(long, env = "XEF_DATA_DIR")]
+    pub data_dir: Option<PathBuf>,
+    /// Workspace to open, created if it doesn't exist.
+    #[arg(
+        short,
+        long,
+        env = "XEF_WORKSPACE",
+        default_value = DEFAULT_WORKSPACE
+    )]
+    pub workspace: String,
+    /// Open damaged data anyway, dropping what can't be loaded and
+    /// saving the rest.
+    #[arg(long)]
+    pub repair: bool,
+}
+
+impl Location
+{
+    fn workspaces(&self) -> Workspaces
+    {
+        let dir = self
+            .data_dir
+            .clone()
+            .unwrap_or_else(Workspaces::default_dir);
+        Workspaces::new(dir)
+    }
+
+    /// Copies [`LEGACY_FILE`] into the chosen workspace when that
+    /// doesn't exist yet, returning the path copied to.
+    pub fn import_legacy(&self) -> Result<Option<PathBuf>>
+    {
+        let legacy = Path::new(LEGACY_FILE);
+        if self.file.is_some() || !legacy.exists() {
+            return Ok(None);
+        }
+        let path = self.workspaces().path(&self.workspace)?;
+        if path.exists() {
+            return Ok(None);
+        }
+        fs::create_dir_all(self.workspaces().dir())?;
+        fs::copy(legacy, &path)?;
+        Ok(Some(path))
+    }
+
+    /// Opens the chosen data in `state`, returning what had to be
+    /// fixed. Unless `writes` is set nothing is created on disk: a
+    /// missing workspace is left empty, or read from [`LEGACY_FILE`]
+    /// in place.
+    pub fn open(
+        &self,
+        state: &mut AppState,
+        writes: bool,
+    ) -> Result<Option<ValidationReport>>
+    {
+        let workspaces = self.workspaces();
+        state.workspaces = Some(workspaces.clone());
+        let path = match &self.file {
+            Some(path) => path.clone(),
+            None => workspaces.path(&self.workspace)?,
+        };
+        let found = writes || path.exists();
+        let legacy = Path::new(LEGACY_FILE);
+        let result = if found && self.file.is_none() {
+            state.open_workspace(&self.workspace, self.repair)
+        } else if found {
+            state.open(storage::open(&path)?, self.repair)
+        } else if self.file.is_none() && legacy.exists() {
+            state.open(storage::open(legacy)?, self.repair)
+        } else {
+            return Ok(None);
+        };
+        result.map_err(|e| match e.downcast_ref::<InventoryError>() {
+            Some(InventoryError::Invalid(_)) => e.context(
+                "the data is damaged, open it with --repair to drop what \
+                 can't be loaded",
+            ),
+            _ => e,
+        })
+    }
+}
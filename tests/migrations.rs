@@ -0,0 +1,112 @@
+use serde_json::{json, Value};
+use uuid::{uuid, Uuid};
+use xef::{
+    database::{migrate, AppData, VERSION},
+    model::{
+        error::InventoryError,
+        inventory::Inventory,
+        quantity::{Quantity, QuantityKind, Unit},
+    },
+};
+
+const CAKE: Uuid = uuid!("c49b2e5e-ab34-4354-beef-88c55216326a");
+const FLOUR: Uuid = uuid!("29f229e7-6978-4383-b32a-5a7173778fce");
+const EGG: Uuid = uuid!("8b0e7c44-5f6e-4a8e-9a57-0f6f7b7f4d21");
+
+fn fixture(name: &str) -> String
+{
+    format!("{}/tests/fixtures/{}", env!("CARGO_MANIFEST_DIR"), name)
+}
+
+fn load(name: &str) -> AppData
+{
+    AppData::load(&fixture(name)).expect("fixture should load")
+}
+
+#[test]
+fn v0_amounts_become_kilograms()
+{
+    let data = load("v0.json");
+    let flour = data.items.iter().find(|i| i.id == FLOUR).unwrap();
+    assert_eq!(flour.amount, Quantity::of(QuantityKind::Mass, 1.0));
+    assert_eq!(flour.trim_loss, 0.0);
+    assert_eq!(flour.density, None);
+
+    let portion = &data.portions[0];
+    assert_eq!(portion.amount.value, 0.5);
+    assert_eq!(portion.amount.unit, Unit::Kilogram);
+
+    let inventory = Inventory::try_from(data).unwrap();
+    assert!((inventory.get_price(CAKE).unwrap() - 4.0).abs() < 1e-9);
+}
+
+#[test]
+fn v0_with_quantities_keeps_kinds()
+{
+    let data = load("v0_quantities.json");
+    let kinds: Vec<QuantityKind> =
+        data.items.iter().map(|i| i.amount.kind()).collect();
+    assert_eq!(
+        kinds,
+        [
+            QuantityKind::Mass,
+            QuantityKind::Volume,
+            QuantityKind::Count
+        ]
+    );
+
+    let egg = data.portions.iter().find(|p| p.component_id == EGG);
+    let egg = egg.unwrap();
+    assert_eq!(egg.amount.value, 0.1);
+    assert_eq!(egg.amount.unit, Unit::Kilogram);
+    assert_eq!(data.portions[0].amount.unit, Unit::Cup);
+
+    assert!(Inventory::try_from(data).is_ok());
+}
+
+#[test]
+fn v1_loads_as_is()
+{
+    let path = fixture("v1.json");
+    let text = std::fs::read_to_string(&path).unwrap();
+    let document: Value = serde_json::from_str(&text).unwrap();
+    assert_eq!(migrate(document.clone()).unwrap(), document);
+
+    let data = load("v1.json");
+    let egg = data.portions.iter().find(|p| p.component_id == EGG);
+    assert_eq!(egg.unwrap().amount.unit, Unit::Piece);
+    assert!(Inventory::try_from(data).is_ok());
+}
+
+#[test]
+fn newer_versions_are_rejected()
+{
+    let document = json!({
+        "version": VERSION + 1,
+        "items": [],
+        "portions": [],
+    });
+    assert!(matches!(
+        AppData::from_document(document),
+        Err(InventoryError::UnsupportedVersion(v)) if v == VERSION + 1
+    ));
+}
+
+#[test]
+fn saved_files_carry_the_current_version()
+{
+    let data = load("v0.json");
+    let path = std::env::temp_dir()
+        .join(format!("xef-migrations-{}.json", Uuid::new_v4()));
+    let path = path.to_str().unwrap();
+    data.save(path).unwrap();
+
+    let text = std::fs::read_to_string(path).unwrap();
+    let document: Value = serde_json::from_str(&text).unwrap();
+    assert_eq!(document["version"], json!(VERSION));
+
+    let reloaded = AppData::load(path).unwrap();
+    std::fs::remove_file(path).unwrap();
+    assert_eq!(reloaded.items.len(), data.items.len());
+    assert_eq!(reloaded.portions.len(), data.portions.len());
+}